@@ -0,0 +1,189 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use crate::{date, trash, Context, Errors, Project};
+
+/// The directory in the workspace where archived projects are kept.
+const ARCHIVE_DIR: &str = ".archive";
+
+pub fn archive_dir(workspace: &Path) -> PathBuf {
+    workspace.join(ARCHIVE_DIR)
+}
+
+/// Writes the directory of a project to `.archive/<name>-<date>.tar.zst`,
+/// with the metadata (see `trash::metadata`) next to it in a .json file.
+/// The directory itself is left as it is. Returns the path of the tarball.
+pub fn create(workspace: &Path, project: &Project) -> Result<PathBuf, Errors> {
+    let dir = archive_dir(workspace);
+    fs::create_dir_all(&dir)?;
+    let base = unused_base(&dir, &project.name);
+    let tarball = dir.join(format!("{}.tar.zst", base));
+
+    let source = project.get_path(workspace);
+    let result = fs::File::create(&tarball)
+        .and_then(|file| zstd::Encoder::new(file, 0))
+        .map_err(Errors::from)
+        .and_then(|encoder| {
+            write_tarball(encoder, &source, &project.name)?.finish()?;
+            Ok(())
+        })
+        .and_then(|()| write_metadata(&dir, &base, workspace, project).map_err(Errors::from));
+    if let Err(error) = result {
+        let _ = fs::remove_file(&tarball);
+        return Err(error).context(|| format!("could not write {}", tarball.to_string_lossy()));
+    }
+    Ok(tarball)
+}
+
+/// Returns `<name>-<date>`, or `<name>-<date>-<number>` if there already
+/// is a tarball with that name in the archive directory.
+fn unused_base(dir: &Path, name: &str) -> String {
+    let mut base = format!("{}-{}", name, date::today());
+    let mut number = 1;
+    while dir.join(format!("{}.tar.zst", base)).exists() {
+        number += 1;
+        base = format!("{}-{}-{}", name, date::today(), number);
+    }
+    base
+}
+
+/// Writes the metadata of a project (see `trash::metadata`) to `<base>.json`.
+fn write_metadata(dir: &Path, base: &str, workspace: &Path, project: &Project) -> io::Result<()> {
+    let metadata = trash::metadata(project, workspace);
+    fs::write(
+        dir.join(format!("{}.json", base)),
+        serde_json::to_string_pretty(&metadata).unwrap_or_default()
+    )
+}
+
+/// Moves the compressed directory of a project to `.archive/<name>-<date>.tar.zst`,
+/// where `create` writes its tarballs, so that the project name can be compressed
+/// again. The metadata is written next to it. Returns the new path of the tarball.
+pub fn set_aside(workspace: &Path, project: &Project) -> Result<PathBuf, Errors> {
+    let dir = archive_dir(workspace);
+    let compressed = compressed_path(workspace, &project.name);
+    let base = unused_base(&dir, &project.name);
+    let tarball = dir.join(format!("{}.tar.zst", base));
+    fs::rename(&compressed, &tarball).context(|| format!(
+        "could not move {} to {}",
+        compressed.to_string_lossy(),
+        tarball.to_string_lossy()
+    ))?;
+    if let Err(error) = write_metadata(&dir, &base, workspace, project) {
+        let _ = fs::rename(&tarball, &compressed);
+        return Err(error).context(|| format!("could not write the metadata of {}", tarball.to_string_lossy()));
+    }
+    Ok(tarball)
+}
+
+/// Returns the names of the compressed directories (`<name>.tar.zst`) in the
+/// archive that belong to no archived project. The tarballs of removed
+/// projects are not included, they have their metadata next to them.
+pub fn orphaned(workspace: &Path, projects: &[Project]) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(archive_dir(workspace)) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+        let name = match file_name.strip_suffix(".tar.zst") {
+            Some(name) => name,
+            None => continue,
+        };
+        if archive_dir(workspace).join(format!("{}.json", name)).exists() {
+            continue;
+        }
+        if !projects.iter().any(|project| project.name == name && project.archived) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Returns the path of the compressed directory of an archived project.
+pub fn compressed_path(workspace: &Path, name: &str) -> PathBuf {
+    archive_dir(workspace).join(format!("{}.tar.zst", name))
+}
+
+/// Returns true if the directory of the project is compressed.
+pub fn is_compressed(workspace: &Path, project: &Project) -> bool {
+    project.archived && compressed_path(workspace, &project.name).is_file()
+}
+
+/// Compresses the directory of a project to `.archive/<name>.tar.zst`
+/// and removes the directory. Returns the path of the tarball.
+pub fn compress(workspace: &Path, project: &Project) -> Result<PathBuf, Errors> {
+    fs::create_dir_all(archive_dir(workspace))?;
+    let tarball = compressed_path(workspace, &project.name);
+    if tarball.exists() {
+        return Err(Errors::DirAlreadyExists(tarball));
+    }
+    let path = project.get_path(workspace);
+
+    let result = fs::File::create(&tarball)
+        .and_then(|file| zstd::Encoder::new(file, 0))
+        .map_err(Errors::from)
+        .and_then(|encoder| {
+            write_tarball(encoder, &path, &project.name)?.finish()?;
+            Ok(())
+        });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&tarball);
+        return Err(error).context(|| format!("could not write {}", tarball.to_string_lossy()));
+    }
+    fs::remove_dir_all(&path)
+        .context(|| format!("could not delete {}", path.to_string_lossy()))?;
+    Ok(tarball)
+}
+
+/// Extracts the compressed directory of a project back into
+/// the workspace, and removes the tarball when it is done.
+pub fn extract(workspace: &Path, project: &Project) -> Result<(), Errors> {
+    let tarball = compressed_path(workspace, &project.name);
+    let path = project.get_path(workspace);
+    if path.exists() {
+        return Err(Errors::DirAlreadyExists(path));
+    }
+
+    let decoder = fs::File::open(&tarball)
+        .and_then(zstd::Decoder::new)
+        .context(|| format!("could not read {}", tarball.to_string_lossy()))?;
+    let mut archive = tar::Archive::new(decoder);
+    let result = archive.entries()
+        .map_err(Errors::from)
+        .and_then(|entries| {
+            for entry in entries {
+                let mut entry = entry?;
+                // the directory in the tarball has the name the project had when
+                // it was compressed, which changes when it is renamed afterwards
+                let relative: PathBuf = entry.path()?.components().skip(1).collect();
+                if relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+                    continue;
+                }
+                let target = path.join(relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                entry.unpack(&target)?;
+            }
+            Ok(())
+        });
+    if let Err(error) = result {
+        let _ = fs::remove_dir_all(&path);
+        return Err(error).context(|| format!("could not extract {}", tarball.to_string_lossy()));
+    }
+    fs::remove_file(&tarball)?;
+    Ok(())
+}
+
+/// Writes a tarball of `source`, where the files are in a directory named `name`.
+/// The writer is returned so that the compression can be finished.
+fn write_tarball<W: Write>(writer: W, source: &Path, name: &str) -> Result<W, Errors> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(name, source)?;
+    Ok(builder.into_inner()?)
+}
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use rusqlite::Connection;
+use crate::config::Config;
+use crate::{templates, Errors, Project};
+
+/// Subcommands where the first argument is the name of a project.
+pub const PROJECT_COMMANDS: &[&str] = &[
+    "cd", "path", "open", "edit", "remove", "fetch", "pick", "archive", "unarchive", "move",
+    "jump",
+];
+
+/// Options whose values are tags. The ones in
+/// `MULTIPLE_TAG_OPTIONS` take any number of tags.
+const TAG_OPTIONS: &[&str] = &["-t", "--tag", "--tags"];
+const MULTIPLE_TAG_OPTIONS: &[&str] = &["--new-tags"];
+
+/// Options whose values are template names.
+const TEMPLATE_OPTIONS: &[&str] = &["-T", "--template"];
+
+/// Options whose values are the names of workspaces from the config.
+const WORKSPACE_OPTIONS: &[&str] = &["-w", "--workspace-name", "--to"];
+
+/// Options that take a value, the ones that are not completed by pile
+/// are only here so that their values are not counted as arguments.
+const VALUE_OPTIONS: &[&str] = &[
+    "-n", "--name", "--where", "-f", "--format", "-c", "--clone", "-e", "--execute",
+    "--new-name", "--workspace", "-j", "--jobs", "--cmd", "-T", "--template",
+    "-w", "--workspace-name", "--to",
+];
+
+/// What should be completed.
+#[derive(Debug, PartialEq)]
+pub enum Candidates {
+    Projects,
+    Tags,
+    Templates,
+    Workspaces,
+}
+
+/// Decides what to complete from the words on the command line
+/// (without "pile" itself), the last word is the one being completed.
+/// Returns None when the shell should fall back to the static completions.
+pub fn context(words: &[String]) -> Option<Candidates> {
+    let (current, before) = words.split_last()?;
+    if current.starts_with('-') {
+        return None;
+    }
+    let previous = before.last().map(String::as_str).unwrap_or_default();
+    if WORKSPACE_OPTIONS.contains(&previous) {
+        return Some(Candidates::Workspaces);
+    }
+
+    // The global options can come before the subcommand
+    let mut before = before;
+    while let Some((first, rest)) = before.split_first() {
+        if !first.starts_with('-') {
+            break;
+        }
+        before = if VALUE_OPTIONS.contains(&first.as_str()) { rest.get(1..)? } else { rest };
+    }
+    // The subcommand and flags are completed statically
+    if before.is_empty() {
+        return None;
+    }
+    let subcommand = before[0].as_str();
+
+    // The values of tag options
+    if TAG_OPTIONS.contains(&previous) {
+        return Some(Candidates::Tags);
+    }
+    if TEMPLATE_OPTIONS.contains(&previous) {
+        return Some(Candidates::Templates);
+    }
+    if let Some(option) = before.iter().rev().find(|word| word.starts_with('-')) {
+        if MULTIPLE_TAG_OPTIONS.contains(&option.as_str()) {
+            return Some(Candidates::Tags);
+        }
+    }
+    if VALUE_OPTIONS.contains(&previous) {
+        return None;
+    }
+
+    // Count the positional arguments before the current word
+    let mut positionals = 0;
+    let mut words = before[1..].iter();
+    while let Some(word) = words.next() {
+        if VALUE_OPTIONS.contains(&word.as_str()) {
+            words.next();
+        } else if !word.starts_with('-') {
+            positionals += 1;
+        }
+    }
+
+    match (subcommand, positionals) {
+        (command, 0) if PROJECT_COMMANDS.contains(&command) => Some(Candidates::Projects),
+        // pile add <name> <tags>...
+        ("add", positionals) if positionals > 0 => Some(Candidates::Tags),
+        // pile template from-project <project>
+        ("template", 1) if before[1] == "from-project" => Some(Candidates::Projects),
+        _ => None,
+    }
+}
+
+/// Returns the workspace given on the command line with --workspace
+/// or -w, so that its projects and tags are completed.
+pub fn workspace_from_words(words: &[String], config: &Config) -> Option<PathBuf> {
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "--workspace" => return words.next().map(PathBuf::from),
+            "-w" | "--workspace-name" => return config.workspaces.get(words.next()?).cloned(),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Returns the project names or tags from the database, the names
+/// of the templates in the workspace or the names of the workspaces.
+pub fn candidates(kind: Candidates, conn: &Connection, workspace: &Path) -> Result<Vec<String>, Errors> {
+    match kind {
+        Candidates::Projects => Ok(Project::fetch_from_db(conn, None, None, None)?
+            .into_iter()
+            .map(|project| project.name)
+            .collect()),
+        Candidates::Tags => Project::all_tags(conn),
+        Candidates::Templates => Ok(templates::list(workspace)?),
+        Candidates::Workspaces => Ok(Config::load()?.workspaces.into_keys().collect()),
+    }
+}
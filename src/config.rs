@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::{Context, Errors};
+use crate::names::NameRules;
+use crate::output::Format;
+
+/// The config file of a workspace, in the root of the workspace.
+const WORKSPACE_CONFIG_FILE: &str = "pile.toml";
+
+/// The settings read from the config files. Everything is optional,
+/// a flag or an environment variable always wins over the config:
+///
+/// ```toml
+/// workspace = "~/projects"
+/// default_tags = ["wip"]
+/// readme_template = "# {{name}}\n\nTags: {{tags}}\n"
+/// editor = "code"
+/// format = "plain"
+///
+/// [aliases]
+/// ls = "list --format plain"
+/// rust = "list --where 'tag:rust and not tag:old'"
+///
+/// [workspaces]
+/// work = "~/work"
+/// scratch = "/tmp/scratch"
+///
+/// [names]
+/// lowercase = true
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The workspace used when there is no --workspace or PILE_WORKSPACE.
+    /// Only read from the user's config file, not from pile.toml
+    pub workspace: Option<PathBuf>,
+    /// Tags added to every new project
+    pub default_tags: Vec<String>,
+    /// The contents of the readme created by "pile add --readme",
+    /// with the same variables as the templates
+    pub readme_template: Option<String>,
+    /// The editor command that "pile open --editor" runs
+    pub editor: Option<String>,
+    /// The output format of "pile list" and "pile status"
+    pub format: Option<String>,
+    /// Commands of your own, "pile <alias> <args>" runs "pile <command> <args>"
+    pub aliases: BTreeMap<String, String>,
+    /// Workspaces that can be picked by name with -w.
+    /// Only read from the user's config file, not from pile.toml
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// How new project names are cleaned up, see `NameRules`
+    pub names: Option<NameRules>,
+}
+
+impl Config {
+    /// Reads the user's config file, see `user_config_path`.
+    /// It is fine if the file does not exist.
+    pub fn load() -> Result<Config, Errors> {
+        match user_config_path() {
+            Some(path) => read(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Adds the settings from the pile.toml of a workspace,
+    /// which win over the ones from the user's config file.
+    pub fn with_workspace(self, workspace: &Path) -> Result<Config, Errors> {
+        let local = read(&workspace_config_path(workspace))?;
+        let mut aliases = self.aliases;
+        aliases.extend(local.aliases);
+        Ok(Config {
+            workspace: self.workspace,
+            default_tags: if local.default_tags.is_empty() { self.default_tags } else { local.default_tags },
+            readme_template: local.readme_template.or(self.readme_template),
+            editor: local.editor.or(self.editor),
+            format: local.format.or(self.format),
+            aliases,
+            workspaces: self.workspaces,
+            names: local.names.or(self.names),
+        })
+    }
+
+    /// Returns the path of a named workspace.
+    pub fn named_workspace(&self, name: &str) -> Result<PathBuf, Errors> {
+        self.workspaces.get(name)
+            .cloned()
+            .ok_or_else(|| Errors::UnknownWorkspace(name.to_string()))
+    }
+
+    /// Returns the rules for project names, by default
+    /// only spaces are replaced.
+    pub fn name_rules(&self) -> NameRules {
+        self.names.clone().unwrap_or_default()
+    }
+
+    /// Returns the readme template, the default is only a heading.
+    pub fn readme_template(&self) -> String {
+        self.readme_template.clone().unwrap_or_else(|| String::from("# {{name}}"))
+    }
+
+    /// Returns the output format, a table if there is none in the config.
+    pub fn format(&self) -> Result<Format, String> {
+        self.format.as_deref().unwrap_or("table").parse()
+    }
+
+    /// Returns the editor command: PILE_EDITOR, the config,
+    /// VISUAL or EDITOR, in that order.
+    pub fn editor(&self) -> Option<String> {
+        env::var("PILE_EDITOR").ok()
+            .or_else(|| self.editor.clone())
+            .or_else(|| env::var("VISUAL").ok())
+            .or_else(|| env::var("EDITOR").ok())
+            .filter(|editor| !editor.trim().is_empty())
+    }
+
+    /// Returns the words an alias expands to, if there is such an alias.
+    pub fn expand_alias(&self, name: &str) -> Option<Result<Vec<String>, Errors>> {
+        let command = self.aliases.get(name)?;
+        Some(split_words(command).ok_or_else(|| {
+            Errors::InvalidConfig(format!("the alias \"{}\" has an unclosed quote", name))
+        }))
+    }
+}
+
+/// Returns the path of the user's config file,
+/// $XDG_CONFIG_HOME/pile/config.toml or ~/.config/pile/config.toml.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))?;
+    Some(config_dir.join("pile").join("config.toml"))
+}
+
+/// Returns the path of the config file of a workspace.
+pub fn workspace_config_path(workspace: &Path) -> PathBuf {
+    workspace.join(WORKSPACE_CONFIG_FILE)
+}
+
+/// Changes the aliases in a config file that `rewrite` returns a new
+/// command for. The file is edited in place, so the comments and the
+/// formatting of everything else are kept. Returns true if it was changed.
+pub fn rewrite_aliases(path: &Path, rewrite: impl Fn(&str) -> Option<String>) -> Result<bool, Errors> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error).context(|| format!("could not read {}", path.to_string_lossy())),
+    };
+    let mut document: toml_edit::DocumentMut = text.parse()
+        .map_err(|error| Errors::InvalidConfig(format!("{}: {}", path.to_string_lossy(), error)))?;
+
+    let mut changed = false;
+    if let Some(aliases) = document.get_mut("aliases").and_then(|item| item.as_table_like_mut()) {
+        for (_, item) in aliases.iter_mut() {
+            let value = match item.as_value_mut() {
+                Some(value) => value,
+                None => continue,
+            };
+            if let Some(command) = value.as_str().and_then(&rewrite) {
+                let decor = value.decor().clone();
+                *value = command.into();
+                *value.decor_mut() = decor;
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        fs::write(path, document.to_string())
+            .context(|| format!("could not write {}", path.to_string_lossy()))?;
+    }
+    Ok(changed)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Reads a config file, a missing file is the same as an empty one.
+fn read(path: &Path) -> Result<Config, Errors> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(error) => return Err(error).context(|| format!("could not read {}", path.to_string_lossy())),
+    };
+    let mut config: Config = toml::from_str(&text)
+        .map_err(|error| Errors::InvalidConfig(format!("{}: {}", path.to_string_lossy(), error)))?;
+    config.workspace = config.workspace.map(|workspace| expand_home(&workspace));
+    for workspace in config.workspaces.values_mut() {
+        *workspace = expand_home(workspace);
+    }
+    Ok(config)
+}
+
+/// Replaces a leading "~" with the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Splits a command line into words, like a shell does (without any
+/// expansions). Returns None if a quote is not closed.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                let escaped = chars.next()?;
+                word.get_or_insert_with(String::new).push(escaped);
+            },
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            },
+            (None, '\\') => {
+                let escaped = chars.next()?;
+                word.get_or_insert_with(String::new).push(escaped);
+            },
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    words.extend(word);
+    Some(words)
+}
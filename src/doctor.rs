@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use rusqlite::Connection;
+use crate::{archive, git, import, Errors, NameRules, Project, ProjectName};
+use crate::names::InvalidName;
+
+/// An inconsistency between the database and the workspace directory.
+#[derive(Debug)]
+pub enum Problem {
+    /// A project in the database without a directory
+    MissingDirectory(String),
+    /// A directory in the workspace without a project
+    UnregisteredDirectory(ProjectName),
+    /// A project or directory name that the name rules would change
+    InvalidName { name: String, cleaned: ProjectName, registered: bool },
+    /// A project or directory name that can not be cleaned up, like
+    /// one with a slash from before project names were checked
+    UnusableName { name: String, reason: InvalidName, registered: bool },
+    /// Several projects that are clones of the same git remote
+    DuplicateRemote { url: String, projects: Vec<String> },
+    /// A compressed directory in the archive without an archived
+    /// project, it stops a new project with the name from being compressed
+    OrphanedArchive(String),
+}
+
+impl Problem {
+    /// A short description of the problem.
+    pub fn description(&self) -> String {
+        match self {
+            Problem::MissingDirectory(name) =>
+                format!("\"{}\" has no directory in the workspace", name),
+            Problem::UnregisteredDirectory(name) =>
+                format!("the directory \"{}\" is not in the database", name),
+            Problem::InvalidName { name, registered: true, .. } =>
+                format!("the project \"{}\" has an invalid name", name),
+            Problem::InvalidName { name, registered: false, .. } =>
+                format!("the directory \"{}\" has an invalid name", name),
+            Problem::UnusableName { name, reason, registered: true } =>
+                format!("the project {:?} has an invalid name, {}", name, reason),
+            Problem::UnusableName { name, reason, registered: false } =>
+                format!("the directory {:?} has an invalid name, {}", name, reason),
+            Problem::DuplicateRemote { url, projects } =>
+                format!("{} are all clones of {}", projects.join(", "), url),
+            Problem::OrphanedArchive(name) =>
+                format!("the compressed directory \"{}.tar.zst\" in the archive has no archived project", name),
+        }
+    }
+
+    /// A description of what `fix` would do.
+    pub fn fix_description(&self) -> String {
+        match self {
+            Problem::MissingDirectory(name) =>
+                format!("remove \"{}\" from the database", name),
+            Problem::UnregisteredDirectory(name) =>
+                format!("import \"{}\"", name),
+            Problem::InvalidName { name, cleaned, registered: true } =>
+                format!("rename \"{}\" to \"{}\"", name, cleaned),
+            Problem::InvalidName { name, cleaned, registered: false } =>
+                format!("rename the directory \"{}\" to \"{}\" and import it", name, cleaned),
+            Problem::UnusableName { name, registered: true, .. } =>
+                format!("remove {:?} from the database, its directory is left alone", name),
+            Problem::UnusableName { registered: false, .. } =>
+                String::from("none, rename the directory by hand"),
+            Problem::DuplicateRemote { .. } =>
+                String::from("none, remove or rename the duplicates by hand"),
+            Problem::OrphanedArchive(name) =>
+                format!("rename it to \"{}-<date>.tar.zst\", like the archives of removed projects", name),
+        }
+    }
+
+    /// Tries to fix the problem. Returns false if
+    /// the problem can not be fixed automatically.
+    pub fn fix(&self, workspace: &Path, conn: &Connection) -> Result<bool, Errors> {
+        match self {
+            Problem::MissingDirectory(name) => {
+                Project::remove_from_db_by_name(name, conn)?;
+            },
+            Problem::UnregisteredDirectory(name) => {
+                import_directory(name, workspace, conn)?;
+            },
+            Problem::InvalidName { name, cleaned, registered: true } => {
+                let mut project = Project::get_from_db_by_name(name, conn)?;
+                project.edit_name(cleaned, conn, workspace)?;
+            },
+            Problem::InvalidName { name, cleaned, registered: false } => {
+                if workspace.join(cleaned).exists() {
+                    return Err(Errors::DirAlreadyExists(workspace.join(cleaned)));
+                }
+                if let Some(taken) = Project::conflicting_name(cleaned.as_str(), conn)? {
+                    return Err(Errors::ProjectNameTaken(taken));
+                }
+                fs::rename(workspace.join(name), workspace.join(cleaned))?;
+                import_directory(cleaned, workspace, conn)?;
+            },
+            Problem::UnusableName { name, registered: true, .. } => {
+                Project::remove_from_db_by_name(name, conn)?;
+            },
+            Problem::OrphanedArchive(name) => {
+                let name = ProjectName::new(name)
+                    .map_err(|reason| Errors::InvalidProjectName(name.clone(), reason))?;
+                archive::set_aside(workspace, &Project::new(name, Vec::new()))?;
+            },
+            Problem::UnusableName { registered: false, .. } | Problem::DuplicateRemote { .. } => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Registers a directory, with the suggested tags and its git remote.
+fn import_directory(name: &ProjectName, workspace: &Path, conn: &Connection) -> Result<(), Errors> {
+    let path = workspace.join(name);
+    let mut project = Project::new(name.clone(), import::suggest_tags(&path));
+    project.remote = git::remote_url(&path);
+    project.add_to_db(conn)
+}
+
+/// Compares the database with the workspace directory and
+/// returns all of the problems that were found.
+pub fn check(workspace: &Path, conn: &Connection, rules: &NameRules) -> Result<Vec<Problem>, Errors> {
+    let mut problems = Vec::new();
+    let projects = Project::fetch_from_db(conn, None, None, None)?;
+
+    for project in projects.iter() {
+        // the path of such a project could be anywhere, so it is not looked at
+        if let Err(reason) = ProjectName::new(&project.name) {
+            problems.push(Problem::UnusableName { name: project.name.clone(), reason, registered: true });
+            continue;
+        }
+        if archive::is_compressed(workspace, project) {
+            continue;
+        }
+        if !project.get_path(workspace).is_dir() {
+            problems.push(Problem::MissingDirectory(project.name.clone()));
+            continue;
+        }
+        match rules.check(&project.name) {
+            Ok(_) => {},
+            Err(reason) => match rules.slug(&project.name) {
+                Ok(cleaned) => problems.push(Problem::InvalidName { name: project.name.clone(), cleaned, registered: true }),
+                Err(_) => problems.push(Problem::UnusableName { name: project.name.clone(), reason, registered: true }),
+            },
+        }
+    }
+
+    for name in import::unregistered_directories(workspace, conn)? {
+        match rules.check(&name) {
+            Ok(name) => problems.push(Problem::UnregisteredDirectory(name)),
+            Err(reason) => match rules.slug(&name) {
+                Ok(cleaned) => problems.push(Problem::InvalidName { name, cleaned, registered: false }),
+                Err(_) => problems.push(Problem::UnusableName { name, reason, registered: false }),
+            },
+        }
+    }
+
+    for name in archive::orphaned(workspace, &projects)? {
+        problems.push(Problem::OrphanedArchive(name));
+    }
+
+    // Group the projects by the url of their origin remote
+    let mut remotes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for project in projects.iter() {
+        let url = project.remote.clone()
+            .or_else(|| git::remote_url(&project.get_path(workspace)));
+        if let Some(url) = url {
+            remotes.entry(url).or_default().push(project.name.clone());
+        }
+    }
+    for (url, projects) in remotes {
+        if projects.len() > 1 {
+            problems.push(Problem::DuplicateRemote { url, projects });
+        }
+    }
+
+    Ok(problems)
+}
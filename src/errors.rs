@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use crate::{config, query};
+use crate::names::InvalidName;
+
+/// Enum of all the possible Errors
+#[derive(Debug)]
+pub enum Errors {
+    /// Another project already has the name
+    ProjectNameTaken(String),
+    DirAlreadyExists(PathBuf),
+    IOError(io::Error),
+    NotImplemented,
+    DatabaseError(rusqlite::Error),
+    /// No project has (or matches) the name
+    ProjectDoesNotExist(String),
+    UnknownSchemaVersion,
+    InvalidTag,
+    InvalidQuery(query::ParseError),
+    DirDoesNotExist(PathBuf),
+    /// The name (as the user typed it) can not be used for a project
+    InvalidProjectName(String, InvalidName),
+    CloneFailed,
+    FetchFailed,
+    InvalidTemplate(String),
+    CommandFailed,
+    CouldNotExecute,
+    /// A command run by pile exited with this (non-zero) status code
+    ExitStatus(i32),
+    InvalidFunctionName,
+    /// The name matches more than one project (and the user was not asked which one)
+    AmbiguousProjectName(Vec<String>),
+    ClipboardFailed,
+    TemplateDoesNotExist,
+    TemplateAlreadyExists,
+    InvalidTemplateName,
+    /// A pre-hook failed, so the operation was not carried out
+    HookFailed(String),
+    UncommittedChanges,
+    NotConfirmed,
+    NotInTrash,
+    /// Fewer than two projects were visited, so `pile -` has nowhere to go
+    NoPreviousProject,
+    AlreadyArchived,
+    NotArchived,
+    /// A config file could not be read, the message says where and why
+    InvalidConfig(String),
+    /// There was no --workspace, PILE_WORKSPACE or workspace in the config
+    NoWorkspace,
+    /// Neither a subcommand nor an alias from the config
+    UnknownCommand(String),
+    NoEditor,
+    /// There is no workspace with the name in the config
+    UnknownWorkspace(String),
+    /// An error together with what pile was doing when it happened,
+    /// like which file it was writing. See `Context`.
+    Context(String, Box<Errors>),
+}
+
+impl Errors {
+    /// The status code pile exits with because of the error:
+    ///
+    /// | Code | Meaning |
+    /// | ---- | ------- |
+    /// | 1 | Any other error, or the user said no |
+    /// | 2 | Invalid input: a name, tag, query, template, config or command |
+    /// | 3 | The project, directory, template, trash entry, previous project or workspace does not exist |
+    /// | 4 | A conflict: the name or directory is taken, several projects match, the project is (not) archived or has uncommitted changes |
+    /// | 5 | A database error |
+    /// | 6 | An IO error |
+    /// | 7 | A command, hook or git failed |
+    ///
+    /// When pile runs a command that fails, it exits with the status of that command.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Errors::Context(_, error) => error.exit_code(),
+            Errors::ExitStatus(code) => *code,
+            Errors::InvalidTag
+            | Errors::InvalidQuery(_)
+            | Errors::InvalidProjectName(..)
+            | Errors::InvalidTemplate(_)
+            | Errors::InvalidFunctionName
+            | Errors::InvalidTemplateName
+            | Errors::InvalidConfig(_)
+            | Errors::NoWorkspace
+            | Errors::UnknownCommand(_)
+            | Errors::NoEditor => 2,
+            Errors::ProjectDoesNotExist(_)
+            | Errors::DirDoesNotExist(_)
+            | Errors::TemplateDoesNotExist
+            | Errors::NotInTrash
+            | Errors::NoPreviousProject
+            | Errors::UnknownWorkspace(_) => 3,
+            Errors::ProjectNameTaken(_)
+            | Errors::DirAlreadyExists(_)
+            | Errors::AmbiguousProjectName(_)
+            | Errors::TemplateAlreadyExists
+            | Errors::UncommittedChanges
+            | Errors::AlreadyArchived
+            | Errors::NotArchived => 4,
+            Errors::DatabaseError(_)
+            | Errors::UnknownSchemaVersion => 5,
+            Errors::IOError(_) => 6,
+            Errors::CloneFailed
+            | Errors::FetchFailed
+            | Errors::CommandFailed
+            | Errors::CouldNotExecute
+            | Errors::HookFailed(_)
+            | Errors::ClipboardFailed => 7,
+            Errors::NotImplemented | Errors::NotConfirmed => 1,
+        }
+    }
+
+    /// Returns the innermost error, the one that caused all of the others.
+    pub fn root_cause(&self) -> &(dyn Error + 'static) {
+        let mut error: &(dyn Error + 'static) = self;
+        while let Some(source) = error.source() {
+            error = source;
+        }
+        error
+    }
+}
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Errors::ProjectNameTaken(name) => write!(f, "the name \"{}\" is already in use", name),
+            Errors::DirAlreadyExists(path) => write!(f, "{} already exists", path.to_string_lossy()),
+            Errors::IOError(_) => write!(f, "an IO error occurred"),
+            Errors::NotImplemented => write!(f, "this feature is not implemented yet"),
+            Errors::DatabaseError(_) => write!(f, "a database error occurred"),
+            Errors::ProjectDoesNotExist(name) => write!(f, "there is no project called \"{}\"", name),
+            Errors::UnknownSchemaVersion => write!(f, "the database was created by a newer version of pile"),
+            Errors::InvalidTag => write!(f, "tags may not contain commas"),
+            Errors::InvalidQuery(error) => write!(f, "invalid query, {}", error),
+            Errors::DirDoesNotExist(path) => write!(f, "the directory {} does not exist", path.to_string_lossy()),
+            Errors::InvalidProjectName(name, reason) => write!(f, "{:?} is not a valid project name, {}", name, reason),
+            Errors::CloneFailed => write!(f, "git clone failed, the project was not created"),
+            Errors::FetchFailed => write!(f, "some of the projects could not be fetched"),
+            Errors::InvalidTemplate(message) => write!(f, "{}", message),
+            Errors::CommandFailed => write!(f, "the command failed in some of the projects"),
+            Errors::CouldNotExecute => write!(f, "failed to execute the command"),
+            Errors::ExitStatus(code) => write!(f, "the command exited with status {}", code),
+            Errors::InvalidFunctionName => write!(f, "the function name may only contain letters, digits, - and _"),
+            Errors::AmbiguousProjectName(names) => write!(f, "the name matches several projects: {}", names.join(", ")),
+            Errors::ClipboardFailed => write!(f, "could not copy to the clipboard"),
+            Errors::TemplateDoesNotExist => write!(f, "there is no such template, see \"pile template list\""),
+            Errors::TemplateAlreadyExists => write!(f, "a template with that name already exists"),
+            Errors::InvalidTemplateName => write!(f, "template names may not contain slashes or start with a dot"),
+            Errors::HookFailed(hook) => write!(f, "the {} hook failed, nothing was changed", hook),
+            Errors::UncommittedChanges => write!(f, "the project has uncommitted changes, use --force to delete it anyway"),
+            Errors::NotConfirmed => write!(f, "nothing was removed (use --yes to skip the question)"),
+            Errors::NotInTrash => write!(f, "there is no such project in the trash, see \"pile restore\""),
+            Errors::NoPreviousProject => write!(f, "no other project was used before this one"),
+            Errors::AlreadyArchived => write!(f, "the project is already archived"),
+            Errors::NotArchived => write!(f, "the project is not archived"),
+            Errors::InvalidConfig(message) => write!(f, "invalid config, {}", message),
+            Errors::NoWorkspace => write!(
+                f,
+                "pile does not know where your projects are, either\n  \
+                set the PILE_WORKSPACE environment variable,\n  \
+                give the --workspace option, or\n  \
+                add workspace = \"<directory>\" to {}",
+                config::user_config_path()
+                    .map_or(String::from("~/.config/pile/config.toml"), |path| path.to_string_lossy().to_string())
+            ),
+            Errors::UnknownCommand(name) => write!(f, "\"{}\" is neither a command nor an alias, see \"pile --help\"", name),
+            Errors::NoEditor => write!(f, "no editor was found, set PILE_EDITOR or EDITOR, or add editor = \"<command>\" to the config"),
+            Errors::UnknownWorkspace(name) => write!(f, "there is no workspace called \"{}\", see \"pile workspaces\"", name),
+            Errors::Context(context, _) => write!(f, "{}", context),
+        }
+    }
+}
+
+impl Error for Errors {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Errors::IOError(error) => Some(error),
+            Errors::DatabaseError(error) => Some(error),
+            // "an IO error occurred" would only be noise between the two
+            Errors::Context(_, error) => match error.as_ref() {
+                Errors::IOError(error) => Some(error),
+                Errors::DatabaseError(error) => Some(error),
+                error => Some(error),
+            },
+            _ => None,
+        }
+    }
+}
+
+// convert IO Errors to the type Errors
+impl From<io::Error> for Errors {
+    fn from(error: io::Error) -> Self {
+        Errors::IOError(error)
+    }
+}
+
+// convert database errors to the type Errors
+impl From<rusqlite::Error> for Errors {
+    fn from(error: rusqlite::Error) -> Self {
+        Errors::DatabaseError(error)
+    }
+}
+
+/// Adds what pile was doing to the error of a Result, e.g.
+/// `fs::create_dir(&path).context(|| format!("could not create {}", path.display()))`
+pub trait Context<T> {
+    fn context<S: Into<String>>(self, context: impl FnOnce() -> S) -> Result<T, Errors>;
+}
+
+impl<T, E: Into<Errors>> Context<T> for Result<T, E> {
+    fn context<S: Into<String>>(self, context: impl FnOnce() -> S) -> Result<T, Errors> {
+        self.map_err(|error| Errors::Context(context().into(), Box::new(error.into())))
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::{program_command, Project};
+
+/// How running the command in one of the projects went.
+pub enum Outcome {
+    Success,
+    /// The command exited with a non-zero status (None if killed by a signal)
+    Failed(Option<i32>),
+    /// The command could not be started
+    Error(String),
+    /// The command was not run since an earlier project failed
+    Skipped,
+}
+
+/// The result of running the command in a project.
+pub struct Report {
+    pub name: String,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// Runs the command in each of the projects, at most `jobs` at a time.
+/// Every line of output is prefixed with the name of the project.
+/// Unless `keep_going` is true, no new commands are started after one has failed.
+/// The reports are returned in the same order as the projects.
+pub fn run(
+    projects: &[Project],
+    workspace: &Path,
+    args: &[String],
+    jobs: usize,
+    keep_going: bool
+    ) -> Vec<Report> {
+
+    let width = projects.iter().map(|project| project.name.len()).max().unwrap_or(0);
+    let next = Mutex::new(0);
+    let failed = AtomicBool::new(false);
+    let reports: Mutex<Vec<Option<Report>>> = Mutex::new(projects.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| loop {
+                let index = {
+                    let mut next = next.lock().unwrap();
+                    *next += 1;
+                    *next - 1
+                };
+                let project = match projects.get(index) {
+                    Some(project) => project,
+                    None => break,
+                };
+
+                let start = Instant::now();
+                let outcome = if failed.load(Ordering::SeqCst) && !keep_going {
+                    Outcome::Skipped
+                } else {
+                    let prefix = format!("[{:width$}] ", project.name, width = width);
+                    run_one(&project.get_path(workspace), args, &prefix)
+                };
+                if let Outcome::Failed(_) | Outcome::Error(_) = outcome {
+                    failed.store(true, Ordering::SeqCst);
+                }
+
+                reports.lock().unwrap()[index] = Some(Report {
+                    name: project.name.clone(),
+                    outcome,
+                    duration: start.elapsed(),
+                });
+            });
+        }
+    });
+
+    reports.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+/// Runs the command in a single directory and prints its output with a prefix.
+fn run_one(path: &Path, args: &[String], prefix: &str) -> Outcome {
+    let child = program_command(args, path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(error) => return Outcome::Error(error.to_string()),
+    };
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(|| print_prefixed(stdout, prefix, &mut io::stdout()));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(|| print_prefixed(stderr, prefix, &mut io::stderr()));
+        }
+    });
+
+    match child.wait() {
+        Ok(status) if status.success() => Outcome::Success,
+        Ok(status) => Outcome::Failed(status.code()),
+        Err(error) => Outcome::Error(error.to_string()),
+    }
+}
+
+/// Copies the lines from `input` to `output`, with a prefix before each line.
+fn print_prefixed(input: impl Read, prefix: &str, output: &mut impl Write) {
+    for line in BufReader::new(input).split(b'\n') {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let line = String::from_utf8_lossy(&line);
+        // a single write per line, so that lines from different projects do not mix
+        let _ = output.write_all(format!("{}{}\n", prefix, line.trim_end_matches('\r')).as_bytes());
+    }
+}
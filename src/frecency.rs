@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::path::Path;
+use rusqlite::{Connection, params, NO_PARAMS};
+use serde_json::{json, Value};
+use crate::output::Record;
+use crate::{date, Context, Errors, Project};
+
+// Frecency ranks the projects by how often and how recently they were
+// used, like zoxide does for directories. Every visit adds one to the
+// visits of a project, and the score is the visits weighed by how long
+// ago the last visit was. When the visits of all projects add up to more
+// than MAX_VISITS they are all scaled down, so that old habits fade.
+
+/// The total of the visits at which they are aged.
+const MAX_VISITS: f64 = 10000.0;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+/// Returns the frecency score of a project with the given visits.
+pub fn score(visits: f64, last_opened_at: Option<i64>, now: i64) -> f64 {
+    let last_opened_at = match last_opened_at {
+        Some(time) => time,
+        None => return 0.0,
+    };
+    let age = now - last_opened_at;
+    if age < HOUR {
+        visits * 4.0
+    } else if age < DAY {
+        visits * 2.0
+    } else if age < WEEK {
+        visits / 2.0
+    } else {
+        visits / 4.0
+    }
+}
+
+/// Records a visit of a project at `now`. Visits are also numbered, so
+/// that the previous project is known even within the same second.
+pub fn record_visit(name: &str, now: i64, conn: &Connection) -> Result<(), Errors> {
+    conn.execute(
+        "UPDATE projects SET
+            visits = visits + 1,
+            last_opened_at = ?1,
+            last_visit = (SELECT COALESCE(MAX(last_visit), 0) + 1 FROM projects)
+        WHERE name = ?2",
+        params![now, name]
+    ).context(|| format!("could not update \"{}\" in the database", name))?;
+
+    let total: f64 = conn.query_row("SELECT COALESCE(SUM(visits), 0) FROM projects", NO_PARAMS, |row| row.get(0))
+        .context(|| "could not read the visits from the database")?;
+    if total > MAX_VISITS {
+        conn.execute("UPDATE projects SET visits = visits * ?1", params![0.9 * MAX_VISITS / total])
+            .context(|| "could not age the visits in the database")?;
+    }
+    Ok(())
+}
+
+/// Returns the frecency scores of all projects at `now`, by name.
+pub fn scores(now: i64, conn: &Connection) -> Result<HashMap<String, f64>, Errors> {
+    conn.prepare("SELECT name, visits, last_opened_at FROM projects")
+        .and_then(|mut stmt| {
+            stmt.query_map(NO_PARAMS, |row| {
+                let (name, visits, last_opened_at): (String, f64, Option<i64>) = (row.get(0)?, row.get(1)?, row.get(2)?);
+                Ok((name, score(visits, last_opened_at, now)))
+            })?.collect()
+        })
+        .context(|| "could not read the visits from the database")
+}
+
+/// Returns the name of the project that was visited before the last one.
+pub fn previous(conn: &Connection) -> Result<Option<String>, Errors> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM projects WHERE last_visit IS NOT NULL
+        ORDER BY last_visit DESC LIMIT 1 OFFSET 1"
+    ).context(|| "could not read the visits from the database")?;
+    let names: Vec<String> = stmt.query_map(NO_PARAMS, |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .context(|| "could not read the visits from the database")?;
+    Ok(names.into_iter().next())
+}
+
+/// A project with its frecency score, printed by `pile recent`.
+pub struct RecentProject<'a> {
+    pub project: &'a Project,
+    pub workspace: &'a Path,
+    pub score: f64,
+}
+
+impl<'a> Record for RecentProject<'a> {
+    const FIELDS: &'static [&'static str] = &["name", "path", "score", "opened"];
+    const TITLES: &'static [&'static str] = &["Project name", "Score", "Last opened"];
+
+    fn field(&self, field: &str) -> String {
+        match field {
+            "name" => self.project.name.clone(),
+            "path" => self.project.get_path(self.workspace).to_string_lossy().to_string(),
+            "score" => format!("{:.1}", self.score),
+            "opened" => self.project.last_opened_at.map(date::format_date).unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![self.field("name"), self.field("score"), self.field("opened")]
+    }
+
+    fn plain(&self) -> String {
+        self.field("name")
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.project.name,
+            "path": self.project.get_path(self.workspace).to_string_lossy(),
+            "score": self.score,
+            "last_opened_at": self.project.last_opened_at,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+/// How well a query matches a project name, better kinds of matches
+/// always win over worse ones regardless of the score within the kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The characters of the query appear in order, e.g. "rtr" in "rust-tracer"
+    Subsequence,
+    /// The query is a part of the name
+    Substring,
+    /// The name starts with the query
+    Prefix,
+    /// The query is the name (ignoring case)
+    Exact,
+}
+
+/// Matches the query against a candidate, ignoring case. Returns the kind of
+/// match and a score where higher is better, or None if they do not match.
+pub fn score(query: &str, candidate: &str) -> Option<(MatchKind, i64)> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+    // shorter candidates are closer matches
+    let length_penalty = candidate.chars().count() as i64;
+
+    if query == candidate {
+        return Some((MatchKind::Exact, 0));
+    }
+    if candidate.starts_with(&query) {
+        return Some((MatchKind::Prefix, -length_penalty));
+    }
+    if let Some(position) = candidate.find(&query) {
+        return Some((MatchKind::Substring, -(position as i64) - length_penalty));
+    }
+
+    // Every character of the query has to be found, in order. Characters at
+    // the start of a word and next to the previous match give a better score.
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = -length_penalty;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.chars() {
+        let found = (position..chars.len()).find(|&i| chars[i] == c)?;
+        let word_start = found == 0 || !chars[found - 1].is_alphanumeric();
+        if word_start {
+            score += 10;
+        }
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        score -= (found - position) as i64;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some((MatchKind::Subsequence, score))
+}
+
+/// Returns the indices of the candidates that match the query,
+/// best match first.
+pub fn rank<S: AsRef<str>>(query: &str, candidates: &[S]) -> Vec<usize> {
+    let mut matches: Vec<((MatchKind, i64), usize)> = candidates.iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| Some((score(query, candidate.as_ref())?, i)))
+        .collect();
+    // stable, so equally good matches keep their order
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Returns the candidates that match the query with the best kind of
+/// match (all prefix matches if there are any, and so on), best first.
+pub fn best_matches<S: AsRef<str>>(query: &str, candidates: &[S]) -> Vec<usize> {
+    let ranked = rank(query, candidates);
+    let best_kind = match ranked.first() {
+        Some(&i) => score(query, candidates[i].as_ref()).map(|(kind, _)| kind),
+        None => return Vec::new(),
+    };
+    ranked.into_iter()
+        .filter(|&i| score(query, candidates[i].as_ref()).map(|(kind, _)| kind) == best_kind)
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Checks if the directory is the root of a git repository.
+pub fn is_repository(path: &Path) -> bool {
+    path.join(".git").exists()
+}
+
+/// Runs git in the given directory and returns the trimmed stdout,
+/// or None if git could not be started or exited with an error.
+pub fn output(path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns the url of the "origin" remote of a repository.
+pub fn remote_url(path: &Path) -> Option<String> {
+    if !is_repository(path) {
+        return None;
+    }
+    output(path, &["config", "--get", "remote.origin.url"])
+        .filter(|url| !url.is_empty())
+}
+
+/// Runs `git fetch`, or `git pull --ff-only` if `pull` is true.
+/// On failure the error line git printed to stderr is returned.
+pub fn fetch(path: &Path, pull: bool) -> Result<(), String> {
+    let args: &[&str] = if pull { &["pull", "--ff-only"] } else { &["fetch"] };
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(args)
+        .output()
+        .map_err(|error| error.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(error_line(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Picks the most useful line of the stderr output of git.
+fn error_line(stderr: &str) -> String {
+    stderr.lines()
+        .find(|line| line.starts_with("fatal:") || line.starts_with("error:"))
+        .or_else(|| stderr.lines().last())
+        .unwrap_or("git exited with an error")
+        .trim()
+        .to_string()
+}
+
+/// A summary of the state of a repository.
+#[derive(Debug, Default, Clone)]
+pub struct Status {
+    /// The current branch, or "(detached)"
+    pub branch: String,
+    /// True if there are uncommitted changes or untracked files
+    pub dirty: bool,
+    /// Commits ahead of and behind the upstream branch, if there is one
+    pub ahead_behind: Option<(u32, u32)>,
+    /// The date of the last commit (YYYY-MM-DD)
+    pub last_commit: Option<String>,
+    /// The number of stashed changes
+    pub stashes: usize,
+}
+
+/// Reads the status of a repository, returns None if
+/// the directory is not a git repository or git failed.
+pub fn status(path: &Path) -> Option<Status> {
+    if !is_repository(path) {
+        return None;
+    }
+    let mut status = Status::default();
+
+    for line in output(path, &["status", "--porcelain=v2", "--branch"])?.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = head.to_string();
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            // formatted as "+<ahead> -<behind>"
+            let counts: Vec<u32> = ab.split(' ')
+                .filter_map(|count| count[1..].parse().ok())
+                .collect();
+            if let [ahead, behind] = counts[..] {
+                status.ahead_behind = Some((ahead, behind));
+            }
+        } else if !line.starts_with('#') {
+            status.dirty = true;
+        }
+    }
+
+    status.last_commit = output(path, &["log", "-1", "--date=short", "--format=%cd"])
+        .filter(|date| !date.is_empty());
+    status.stashes = output(path, &["stash", "list"])
+        .map_or(0, |stashes| stashes.lines().count());
+
+    Some(status)
+}
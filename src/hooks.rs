@@ -0,0 +1,225 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use crate::{shell_command, Context, Errors, Project};
+
+/// The directory in the workspace with the hooks, hidden like the templates.
+const HOOKS_DIR: &str = ".hooks";
+
+/// A file in the hooks directory with hooks as shell commands, one per line:
+///
+/// ```text
+/// post-add = git init
+/// post-open = direnv allow
+/// ```
+const CONFIG_FILE: &str = "hooks.conf";
+
+/// The output of the hooks while it is captured, see `capture_output`.
+static CAPTURED: Mutex<Option<String>> = Mutex::new(None);
+
+/// The things that happen to projects which hooks can be run for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Add,
+    Rename,
+    Tags,
+    Remove,
+    Open,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Add => "add",
+            Event::Rename => "rename",
+            Event::Tags => "tags",
+            Event::Remove => "remove",
+            Event::Open => "open",
+        }
+    }
+}
+
+/// Hooks run before an event can stop it by failing,
+/// the hooks run after it can not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum When {
+    Pre,
+    Post,
+}
+
+/// Returns the name of a hook, like "pre-add".
+pub fn hook_name(when: When, event: Event) -> String {
+    let when = match when {
+        When::Pre => "pre",
+        When::Post => "post",
+    };
+    format!("{}-{}", when, event.name())
+}
+
+/// Returns the hooks directory of a workspace.
+pub fn hooks_dir(workspace: &Path) -> PathBuf {
+    workspace.join(HOOKS_DIR)
+}
+
+/// Returns the commands from the config file for the hook, in order.
+fn configured_commands(workspace: &Path, name: &str) -> io::Result<Vec<String>> {
+    let text = match fs::read_to_string(hooks_dir(workspace).join(CONFIG_FILE)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    Ok(text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .filter(|(hook, _)| hook.trim() == name)
+        .map(|(_, command)| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+        .collect())
+}
+
+/// Changes the commands in the config file that `rewrite` returns a new
+/// command for. Returns true if the file was changed.
+pub fn rewrite_commands(workspace: &Path, rewrite: impl Fn(&str) -> Option<String>) -> Result<bool, Errors> {
+    let path = hooks_dir(workspace).join(CONFIG_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error).context(|| format!("could not read {}", path.to_string_lossy())),
+    };
+    let mut changed = false;
+    let lines: Vec<String> = text.lines()
+        .map(|line| match line.split_once('=') {
+            Some((hook, command)) if !line.trim().starts_with('#') => match rewrite(command) {
+                Some(command) => {
+                    changed = true;
+                    format!("{}={}", hook, command)
+                },
+                None => line.to_string(),
+            },
+            _ => line.to_string(),
+        })
+        .collect();
+    if changed {
+        let mut text = lines.join("\n");
+        text.push('\n');
+        fs::write(&path, text).context(|| format!("could not write {}", path.to_string_lossy()))?;
+    }
+    Ok(changed)
+}
+
+/// Starts or stops capturing the output of the hooks (and the warnings about
+/// them), instead of writing it to stderr. The TUI captures it while it is on
+/// the screen, where the output would end up in the middle of its drawing.
+pub fn capture_output(capture: bool) {
+    if let Ok(mut captured) = CAPTURED.lock() {
+        *captured = if capture { Some(String::new()) } else { None };
+    }
+}
+
+/// Returns the output that was captured since the last call, see `capture_output`.
+pub fn take_output() -> String {
+    match CAPTURED.lock() {
+        Ok(mut captured) => captured.as_mut().map(std::mem::take).unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Writes output of a hook to stderr, or to the captured output.
+fn write_output(output: &[u8]) {
+    if let Ok(mut captured) = CAPTURED.lock() {
+        if let Some(captured) = captured.as_mut() {
+            captured.push_str(&String::from_utf8_lossy(output));
+            return;
+        }
+    }
+    let _ = io::stderr().write_all(output);
+}
+
+/// Runs a hook and returns true if it succeeded. Its output goes to stderr
+/// while it runs, unless the output is captured.
+fn run_command(command: &mut Command) -> bool {
+    let capturing = CAPTURED.lock().is_ok_and(|captured| captured.is_some());
+    if !capturing {
+        return command.stdout(io::stderr()).status().is_ok_and(|status| status.success());
+    }
+    match command.stdout(Stdio::piped()).stderr(Stdio::piped()).output() {
+        Ok(output) => {
+            write_output(&output.stdout);
+            write_output(&output.stderr);
+            output.status.success()
+        },
+        Err(_) => false,
+    }
+}
+
+/// Returns true if the file can be run by itself.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs the hooks for an event: first the executable named after the hook
+/// in the hooks directory (e.g. ".hooks/pre-add"), then the commands from
+/// the config file. The hooks get the project in environment variables,
+/// `extra` are added to those. Their output goes to stderr, so that it is
+/// not mixed up with paths printed for scripts, see also `capture_output`.
+///
+/// If a pre-hook fails the remaining hooks are not run and an error is
+/// returned. A failing post-hook is only reported.
+pub fn run(
+    workspace: &Path,
+    when: When,
+    event: Event,
+    project: &Project,
+    extra: &[(&str, String)]
+    ) -> Result<(), Errors> {
+
+    let name = hook_name(when, event);
+    let mut commands: Vec<Command> = Vec::new();
+
+    let executable = hooks_dir(workspace).join(&name);
+    if is_executable(&executable) {
+        commands.push(Command::new(executable));
+    }
+    let project_path = project.get_path(workspace);
+    // Before "add" the directory does not exist, and after "remove" it might not
+    let directory = if project_path.is_dir() { project_path.clone() } else { workspace.to_path_buf() };
+    let configured = configured_commands(workspace, &name).context(|| format!(
+        "could not read {}",
+        hooks_dir(workspace).join(CONFIG_FILE).to_string_lossy()
+    ))?;
+    for command in configured {
+        commands.push(shell_command(&command, &directory));
+    }
+
+    for mut command in commands {
+        command
+            .current_dir(&directory)
+            .env("PILE_HOOK", &name)
+            .env("PILE_WORKSPACE", workspace)
+            .env("PILE_PROJECT_NAME", &project.name)
+            .env("PILE_PROJECT_PATH", &project_path)
+            .env("PILE_PROJECT_TAGS", project.tags.join(","))
+            .stdin(Stdio::null());
+        for (key, value) in extra {
+            command.env(key, value);
+        }
+
+        if !run_command(&mut command) {
+            match when {
+                When::Pre => return Err(Errors::HookFailed(name)),
+                When::Post => write_output(format!("Warning: the {} hook failed\n", name).as_bytes()),
+            }
+        }
+    }
+    Ok(())
+}
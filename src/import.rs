@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::Path;
+use rusqlite::Connection;
+use crate::{Errors, Project};
+
+/// Manifest files that hint at what kind of project a directory is,
+/// and the tag that is suggested when one of them is found.
+const MANIFESTS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust"),
+    ("package.json", "javascript"),
+    ("tsconfig.json", "typescript"),
+    ("pyproject.toml", "python"),
+    ("setup.py", "python"),
+    ("requirements.txt", "python"),
+    ("go.mod", "go"),
+    ("pom.xml", "java"),
+    ("build.gradle", "java"),
+    ("Gemfile", "ruby"),
+    ("mix.exs", "elixir"),
+    ("stack.yaml", "haskell"),
+    ("CMakeLists.txt", "c++"),
+];
+
+/// Returns the names of the directories in the workspace that
+/// are not registered in the database, sorted by name.
+/// Hidden directories (such as `.git` or `.trash`) are skipped.
+pub fn unregistered_directories(workspace: &Path, conn: &Connection) -> Result<Vec<String>, Errors> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(workspace)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || Project::name_taken(&name, conn)? {
+            continue;
+        }
+        names.push(name);
+    }
+    names.sort_by_key(|name| name.to_lowercase());
+    Ok(names)
+}
+
+/// Suggests tags for a directory based on the manifest files in it.
+pub fn suggest_tags(path: &Path) -> Vec<String> {
+    let tags: Vec<String> = MANIFESTS.iter()
+        .filter(|(file, _)| path.join(file).exists())
+        .map(|(_, tag)| tag.to_string())
+        .collect();
+    Project::clean_tags(&tags)
+}
@@ -1,53 +1,48 @@
+use std::collections::BTreeMap;
+use std::error::Error;
 use std::fs;
 use std::io;
-use std::process::Output;
+use std::process::ExitStatus;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use clipboard::ClipboardProvider;
 use clipboard::ClipboardContext;
 use rusqlite::{Connection, params};
 use rusqlite::NO_PARAMS;
-use prettytable::{Table, Row, Cell};
-use prettytable::format;
-use open;
-
-// Todo:
-// * migrate
-// * keep track from where things are git cloned
-//      + create a fetch command
-// * better errors, when a conflicting dir exists for instance
-
-/// Enum of all the possible Errors
-pub enum Errors {
-    ProjectNameTaken,
-    DirAlreadyExists,
-    IOError,
-    NotImplemented,
-    DatabaseError,
-    CouldNotGetProject,
-    ProjectDoesNotExist,
-    FailedToRemoveProject
-}
-
-// convert IO Errors to the type Errors
-impl From<std::io::Error> for Errors {
-    fn from(error: std::io::Error) -> Self {
-        if error.kind() == std::io::ErrorKind::AlreadyExists {
-            Errors::DirAlreadyExists
-        } else {
-            Errors::IOError
-        }
-    }
-}
 
-// convert IO Errors to the type Errors
-impl From<rusqlite::Error> for Errors {
-    fn from(_: rusqlite::Error) -> Self {
-        Errors::DatabaseError
-    }
-}
+pub mod archive;
+pub mod complete;
+pub mod config;
+pub mod date;
+pub mod doctor;
+pub mod errors;
+pub mod foreach;
+pub mod frecency;
+pub mod fuzzy;
+pub mod git;
+pub mod hooks;
+pub mod import;
+pub mod migrations;
+pub mod names;
+pub mod output;
+pub mod pick;
+pub mod query;
+pub mod rename;
+pub mod shell;
+pub mod sort;
+pub mod status;
+pub mod templates;
+pub mod trash;
+pub mod tui;
+pub mod workspaces;
 
+use prettytable::{Row, Cell};
+use output::{Format, PlainField};
+use query::Expr;
+use hooks::{Event, When};
+pub use errors::{Context, Errors};
+pub use names::{NameRules, ProjectName};
 
 /// Opens the documentation/github in a browser window.
 pub fn open_documentation() -> Result<(), Errors> {
@@ -64,83 +59,706 @@ pub fn open_workspace(workspace: PathBuf) -> Result<(), Errors> {
     Ok(())
 }
 
-/// Prints a list of all the projects
+/// Prints a list of all the projects. Archived projects are left
+/// out, unless `archived` (only archived ones) or `all` is true.
+/// The table only has the date columns if `dates` is true.
+#[allow(clippy::too_many_arguments)]
 pub fn print_list(
     workspace: PathBuf,
     name: Option<String>,
-    tag: Option<String>
+    tag: Option<String>,
+    where_query: Option<String>,
+    archived: bool,
+    all: bool,
+    order: &sort::Order,
+    dates: bool,
+    format: Format
     ) -> Result<(), Errors> {
 
+    let where_query = parse_where(where_query)?;
     let conn = get_connection(&workspace)?;
-    let projects = Project::fetch_from_db(&conn, name, tag)?;
+    let mut projects: Vec<Project> = Project::fetch_from_db(&conn, name, tag, where_query.as_ref())?
+        .into_iter()
+        .filter(|project| all || project.archived == archived)
+        .collect();
+    order.apply(&mut projects, |project| (project, &workspace));
+    order.truncate(&mut projects);
 
-    if projects.is_empty(){
+    if projects.is_empty() && format == Format::Table {
         println!("No projects where found :(");
         return Ok(());
     }
 
-    // Create a table
-    let mut table = Table::new();
-    table.set_titles(Row::new(vec![Cell::new("Project name"), Cell::new("Tags")]));
-    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
-    for project in projects.iter() {
+    let records = output::project_records(&projects, &workspace, PlainField::Name, dates);
+    print!("{}", output::render(&records, &format)?);
+    Ok(())
+}
+
+/// Prints the projects of all the named workspaces, filtered and
+/// sorted like in `print_list`. The order is over all of the projects.
+#[allow(clippy::too_many_arguments)]
+pub fn print_list_all_workspaces(
+    workspaces: &BTreeMap<String, PathBuf>,
+    name: Option<String>,
+    tag: Option<String>,
+    where_query: Option<String>,
+    archived: bool,
+    all: bool,
+    order: &sort::Order,
+    dates: bool,
+    format: Format
+    ) -> Result<(), Errors> {
+
+    if workspaces.is_empty() {
+        print_no_workspaces();
+        return Ok(());
+    }
+    let where_query = parse_where(where_query)?;
+
+    let mut found: Vec<(&str, &Path, Vec<Project>)> = Vec::new();
+    for (workspace_name, workspace) in workspaces.iter() {
+        if !workspace.is_dir() {
+            eprintln!("Warning: the workspace {} does not exist", workspace.to_string_lossy());
+            continue;
+        }
+        let conn = get_connection(workspace)
+            .context(|| format!("could not read the workspace {}", workspace_name))?;
+        let projects = Project::fetch_from_db(&conn, name.clone(), tag.clone(), where_query.as_ref())?
+            .into_iter()
+            .filter(|project| all || project.archived == archived)
+            .collect();
+        found.push((workspace_name, workspace, projects));
+    }
+
+    let mut entries: Vec<(&str, &Path, &Project)> = found.iter()
+        .flat_map(|(workspace_name, workspace, projects)| {
+            projects.iter().map(move |project| (*workspace_name, *workspace, project))
+        })
+        .collect();
+    order.apply(&mut entries, |(_, workspace, project)| (*project, *workspace));
+    order.truncate(&mut entries);
+
+    let records: Vec<output::WorkspaceProjectRecord> = entries.into_iter()
+        .map(|(workspace_name, workspace, project)| output::WorkspaceProjectRecord {
+            workspace_name,
+            record: output::ProjectRecord { project, workspace, plain: PlainField::Name, dates },
+        })
+        .collect();
+
+    if records.is_empty() && format == Format::Table {
+        println!("No projects where found :(");
+        return Ok(());
+    }
+    print!("{}", output::render(&records, &format)?);
+    Ok(())
+}
+
+/// Prints the named workspaces with the number of projects in them.
+/// The one that is used when no workspace is given is marked with a *.
+pub fn workspaces_command(workspaces: &BTreeMap<String, PathBuf>, current: Option<&Path>) -> Result<(), Errors> {
+    if workspaces.is_empty() {
+        print_no_workspaces();
+        return Ok(());
+    }
+    let mut table = output::new_table(&["", "Name", "Path", "Projects"]);
+    for (name, workspace) in workspaces.iter() {
+        let marker = match current {
+            Some(current) if workspaces::same_directory(current, workspace) => "*",
+            _ => "",
+        };
+        let projects = if !workspace.is_dir() {
+            String::from("does not exist")
+        } else {
+            workspaces::project_count(workspace).unwrap_or(0).to_string()
+        };
         table.add_row(Row::new(vec![
-            Cell::new(&project.name),
-            Cell::new(&project.tags.join(", ")
-        )]));
+            Cell::new(marker),
+            Cell::new(name),
+            Cell::new(&workspace.to_string_lossy()),
+            Cell::new(&projects)
+        ]));
     }
     table.printstd();
+    Ok(())
+}
+
+fn print_no_workspaces() {
+    println!("There are no named workspaces, add them to {} like this:",
+        config::user_config_path()
+            .map_or(String::from("~/.config/pile/config.toml"), |path| path.to_string_lossy().to_string()));
+    println!();
+    println!("[workspaces]");
+    println!("work = \"~/work\"");
+    println!("personal = \"~/projects\"");
+}
+
+/// Moves a project to another workspace, both its directory (or its
+/// compressed archive) and its row in the database.
+///
+/// The two databases can not share a transaction, so the steps are done one
+/// after the other: the project is added to the target database, then the
+/// directory is moved, then the project is removed from this database. When
+/// a step fails the earlier ones are undone, so the project ends up in one
+/// of the workspaces. If undoing fails too, the error says what is left.
+/// A directory that was copied but could not be removed is left for the user.
+pub fn move_command(workspace: PathBuf, name: String, target: PathBuf) -> Result<(), Errors> {
+    if !target.is_dir() {
+        return Err(Errors::DirDoesNotExist(target));
+    }
+    let conn = get_connection(&workspace)?;
+    let target_conn = get_connection(&target)?;
+    let project = resolve_exact_project(&name, &conn)?;
+    if let Some(taken) = Project::conflicting_name(&project.name, &target_conn)? {
+        return Err(Errors::ProjectNameTaken(taken));
+    }
+
+    // The compressed directory of an archived project is moved instead
+    let (from, to) = if archive::is_compressed(&workspace, &project) {
+        (archive::compressed_path(&workspace, &project.name), archive::compressed_path(&target, &project.name))
+    } else {
+        (project.get_path(&workspace), project.get_path(&target))
+    };
+    if to.exists() {
+        return Err(Errors::DirAlreadyExists(to));
+    }
+
+    in_savepoint(&target_conn, || project.add_to_db(&target_conn))?;
+    let undo_add = |error: Errors| match Project::remove_from_db_by_name(&project.name, &target_conn) {
+        Ok(()) => error,
+        Err(undo) => Errors::Context(format!(
+            "\"{}\" is now in both workspaces, could not remove it from {} ({})",
+            project.name,
+            target.to_string_lossy(),
+            undo
+        ), Box::new(error)),
+    };
+
+    let moved = from.exists();
+    // A directory that was copied to the other file system, but could not be
+    // removed completely, is not moved back: what is left of it is damaged
+    let mut left_over = None;
+    if moved {
+        left_over = workspaces::move_path(&from, &to).map_err(undo_add)?;
+    }
+    let removed = in_savepoint(&conn, || Project::remove_from_db_by_name(&project.name, &conn));
+    if let Err(error) = removed {
+        if let Some(left_over) = left_over {
+            return Err(Errors::Context(format!(
+                "\"{}\" is now in both workspaces, its directory was copied to {} but part of {} is left ({}), \
+                remove that and the project in {} by hand",
+                project.name,
+                to.to_string_lossy(),
+                from.to_string_lossy(),
+                left_over,
+                workspace.to_string_lossy()
+            ), Box::new(error)));
+        }
+        if moved {
+            match workspaces::move_path(&to, &from) {
+                Ok(None) => {},
+                Ok(Some(undo)) => eprintln!(
+                    "Warning: the directory was moved back to {}, but part of {} is left ({})",
+                    from.to_string_lossy(),
+                    to.to_string_lossy(),
+                    undo
+                ),
+                Err(undo) => return Err(Errors::Context(format!(
+                    "\"{}\" is now in both workspaces and its directory is {}, could not move it back ({})",
+                    project.name,
+                    to.to_string_lossy(),
+                    undo
+                ), Box::new(error))),
+            }
+        }
+        return Err(undo_add(error));
+    }
 
+    println!("The project \"{}\" was moved to {}", project.name, target.to_string_lossy());
+    if !moved {
+        println!("Note: it had no directory, so only the database was changed");
+    } else {
+        println!("{}", to.to_string_lossy());
+    }
+    if let Some(left_over) = left_over {
+        eprintln!(
+            "Warning: the directory was copied, but part of {} could not be removed ({}), delete what is left by hand",
+            from.to_string_lossy(),
+            left_over
+        );
+    }
+    Ok(())
+}
+
+/// Parses the query given to --where, if any.
+fn parse_where(where_query: Option<String>) -> Result<Option<Expr>, Errors> {
+    match where_query {
+        Some(query) => Ok(Some(Expr::parse(&query).map_err(Errors::InvalidQuery)?)),
+        None => Ok(None)
+    }
+}
+
+/// Prints the git status of all the projects that are git repositories.
+/// The projects are filtered the same way as in `print_list`, and if
+/// `unsaved` is true only projects with changes that are not pushed are shown.
+pub fn status_command(
+    workspace: PathBuf,
+    name: Option<String>,
+    tag: Option<String>,
+    where_query: Option<String>,
+    unsaved: bool,
+    format: Format
+    ) -> Result<(), Errors> {
+
+    let where_query = parse_where(where_query)?;
+    let conn = get_connection(&workspace)?;
+    let projects = without_archived(Project::fetch_from_db(&conn, name, tag, where_query.as_ref())?);
+
+    let statuses: Vec<status::ProjectStatus> = projects.iter()
+        .filter_map(|project| Some(status::ProjectStatus {
+            status: git::status(&project.get_path(&workspace))?,
+            project
+        }))
+        .filter(|status| !unsaved || status.is_unsaved())
+        .collect();
+
+    if statuses.is_empty() && format == Format::Table {
+        println!("No git projects where found :(");
+        return Ok(());
+    }
+
+    print!("{}", output::render(&statuses, &format)?);
     Ok(())
 }
 
-/// Removes a project from the database.
-pub fn remove_project(workspace: PathBuf, name: String) -> Result<(), Errors> {
+/// What `remove_project` does with the directory of the project.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoveDirectory {
+    /// Leave it in the workspace
+    Keep,
+    /// Delete it and everything in it
+    Delete,
+    /// Move it to the trash, see `restore_command`
+    Trash,
+    /// Write it to a tarball in the archive, then delete it
+    Archive,
+}
+
+/// Removes a project from the database, and its directory as well unless
+/// `directory` is `Keep`. Deleting a directory has to be confirmed (unless
+/// `yes` is true) and is refused if git has uncommitted changes in it
+/// (unless `force` is true). Unlike the other commands the name has to be
+/// exact, so that the wrong project is never removed. For a compressed
+/// project the same is done with its tarball in the archive.
+pub fn remove_project(
+    workspace: PathBuf,
+    name: String,
+    directory: RemoveDirectory,
+    yes: bool,
+    force: bool
+    ) -> Result<(), Errors> {
+
     let conn = get_connection(&workspace)?;
 
     // Check if the project name actually exists
-    if !Project::name_taken(&name, &conn) {
-        return Err(Errors::ProjectDoesNotExist);
+    if !Project::name_taken(&name, &conn)? {
+        return Err(Errors::ProjectDoesNotExist(name));
     }
 
-    Project::remove_from_db_by_name(&name, &conn)?;
+    let project = Project::get_from_db_by_name(&name, &conn)?;
+    // The directory of a compressed project is its tarball in the archive
+    let compressed = archive::is_compressed(&workspace, &project);
+    let path = if compressed {
+        archive::compressed_path(&workspace, &project.name)
+    } else {
+        project.get_path(&workspace)
+    };
+    if directory != RemoveDirectory::Keep && !compressed && !path.is_dir() {
+        return Err(Errors::DirDoesNotExist(path));
+    }
+    if directory == RemoveDirectory::Delete {
+        if !compressed && !force && git::status(&path).is_some_and(|status| status.dirty) {
+            return Err(Errors::UncommittedChanges);
+        }
+        let question = format!("Delete {} and everything in it?", path.to_string_lossy());
+        let confirmed = yes || (pick::is_interactive() && pick::confirm(&question));
+        if !confirmed {
+            return Err(Errors::NotConfirmed);
+        }
+    }
+
+    hooks::run(&workspace, When::Pre, Event::Remove, &project, &[])?;
+
+    // The project stays in the database if anything happens to the directory
+    let message = in_savepoint(&conn, || {
+        Project::remove_from_db_by_name(&name, &conn)?;
+        match directory {
+            // the tarball is kept under another name, so that it does not
+            // get in the way of a new project with the same name
+            RemoveDirectory::Keep if compressed => {
+                let tarball = archive::set_aside(&workspace, &project)?;
+                Ok(format!("Note: the compressed directory has not been removed, it is kept in {}", tarball.to_string_lossy()))
+            },
+            RemoveDirectory::Keep => {
+                Ok(String::from("Note: the actual directory has not been removed"))
+            },
+            RemoveDirectory::Delete if compressed => {
+                fs::remove_file(&path)
+                    .context(|| format!("could not delete {}", path.to_string_lossy()))?;
+                Ok(format!("{} has been deleted", path.to_string_lossy()))
+            },
+            RemoveDirectory::Delete => {
+                fs::remove_dir_all(&path)
+                    .context(|| format!("could not delete {}", path.to_string_lossy()))?;
+                Ok(format!("{} has been deleted", path.to_string_lossy()))
+            },
+            // the trash has directories, so that "pile restore" can move them back
+            RemoveDirectory::Trash if compressed => {
+                archive::extract(&workspace, &project)?;
+                match trash::put(&workspace, &project) {
+                    Ok(id) => Ok(format!("The directory was moved to the trash, bring it back with \"pile restore {}\"", id)),
+                    Err(error) => {
+                        let _ = archive::compress(&workspace, &project);
+                        Err(error)
+                    },
+                }
+            },
+            RemoveDirectory::Trash => {
+                let id = trash::put(&workspace, &project)?;
+                Ok(format!("The directory was moved to the trash, bring it back with \"pile restore {}\"", id))
+            },
+            RemoveDirectory::Archive if compressed => {
+                let tarball = archive::set_aside(&workspace, &project)?;
+                Ok(format!("The directory was archived to {}", tarball.to_string_lossy()))
+            },
+            RemoveDirectory::Archive => {
+                let tarball = archive::create(&workspace, &project)?;
+                fs::remove_dir_all(&path)
+                    .context(|| format!("could not delete {}", path.to_string_lossy()))?;
+                Ok(format!("The directory was archived to {}", tarball.to_string_lossy()))
+            },
+        }
+    })?;
     println!("The project \"{}\" was removed from the database", name);
-    println!("Note: the actual directory has not been removed");
+    println!("{}", message);
+    hooks::run(&workspace, When::Post, Event::Remove, &project, &[])
+}
+
+/// Archives a project, which hides it from most commands. If `compress` is
+/// true the directory is compressed to a .tar.zst in the archive directory.
+pub fn archive_command(workspace: PathBuf, name: String, compress: bool) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let mut project = resolve_exact_project(&name, &conn)?;
+    if project.archived {
+        return Err(Errors::AlreadyArchived);
+    }
+    if compress && !project.get_path(&workspace).is_dir() {
+        return Err(Errors::DirDoesNotExist(project.get_path(&workspace)));
+    }
+
+    // The project is not archived if the compression fails
+    let tarball = in_savepoint(&conn, || {
+        project.set_archived(true, &conn)?;
+        if compress {
+            return Ok(Some(archive::compress(&workspace, &project)?));
+        }
+        Ok(None)
+    })?;
+    println!("The project \"{}\" was archived", project.name);
+    if let Some(tarball) = tarball {
+        println!("The directory was compressed to {}", tarball.to_string_lossy());
+    }
+    Ok(())
+}
+
+/// Unarchives a project, and extracts its directory if it was compressed.
+pub fn unarchive_command(workspace: PathBuf, name: String) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let mut project = resolve_exact_project(&name, &conn)?;
+    if !project.archived {
+        return Err(Errors::NotArchived);
+    }
+
+    let compressed = archive::is_compressed(&workspace, &project);
+    in_savepoint(&conn, || {
+        project.set_archived(false, &conn)?;
+        if compressed {
+            archive::extract(&workspace, &project)?;
+        }
+        Ok(())
+    })?;
+    println!("The project \"{}\" is no longer archived", project.name);
+    if compressed {
+        println!("{}", project.get_path(&workspace).to_string_lossy());
+    }
     Ok(())
 }
-/// This should def be changed.
+
+/// Leaves out the archived projects.
+fn without_archived(projects: Vec<Project>) -> Vec<Project> {
+    projects.into_iter().filter(|project| !project.archived).collect()
+}
+
+/// Brings a project back from the trash, `name` is a project name or the id
+/// of an entry in the trash. Without a name the trash is listed.
+pub fn restore_command(workspace: PathBuf, name: Option<String>) -> Result<(), Errors> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let entries = trash::entries(&workspace)?;
+            if entries.is_empty() {
+                println!("The trash is empty");
+                return Ok(());
+            }
+            let mut table = output::new_table(&["Id", "Project name", "Tags", "Removed"]);
+            for entry in entries.iter() {
+                table.add_row(Row::new(vec![
+                    Cell::new(&entry.id),
+                    Cell::new(&entry.project.name),
+                    Cell::new(&entry.project.tags.join(", ")),
+                    Cell::new(&date::format_date(entry.removed_at))
+                ]));
+            }
+            table.printstd();
+            println!("Restore one of them with \"pile restore <id>\"");
+            return Ok(());
+        }
+    };
+
+    let conn = get_connection(&workspace)?;
+    let entry = trash::find(&workspace, &name)?;
+    if let Some(taken) = Project::conflicting_name(&entry.project.name, &conn)? {
+        return Err(Errors::ProjectNameTaken(taken));
+    }
+    in_savepoint(&conn, || {
+        entry.project.add_to_db(&conn)?;
+        trash::take_out(&workspace, &entry)
+    })?;
+    println!("The project \"{}\" was restored", entry.project.name);
+    println!("{}", entry.project.get_path(&workspace).to_string_lossy());
+    Ok(())
+}
+
 /// Returns the path to a specific project.
-pub fn get_project_path(name: String, workspace: &PathBuf) -> Result<PathBuf, Errors> {
+/// The name does not have to be exact, see `resolve_project`.
+pub fn get_project_path(name: String, workspace: &Path) -> Result<PathBuf, Errors> {
+    let conn = get_connection(workspace)?;
+    let mut project = resolve_project(&name, &conn)?;
+    mark_opened(&mut project, &conn);
+    Ok(project.get_path(workspace))
+}
+
+/// Records that the project was opened. The command still works if
+/// the database can not be written to, so then it is only a warning.
+fn mark_opened(project: &mut Project, conn: &Connection) {
+    if let Err(error) = project.mark_opened(conn) {
+        eprintln!("Warning: {}", error);
+    }
+}
+
+/// Cleans up a name that the user typed with the name rules.
+fn slug(name: &str, rules: &NameRules) -> Result<ProjectName, Errors> {
+    rules.slug(name).map_err(|reason| Errors::InvalidProjectName(name.to_string(), reason))
+}
+
+/// Finds the project the user meant with `name`, see `Project::find`.
+/// If several projects match and pile runs in a terminal,
+/// the user is asked to pick one of them.
+fn resolve_project(name: &str, conn: &Connection) -> Result<Project, Errors> {
+    match Project::find(name, conn) {
+        Err(Errors::AmbiguousProjectName(names)) if pick::is_interactive() => {
+            match pick::choose(name, &names) {
+                Some(choice) => Project::get_from_db_by_name(&names[choice], conn),
+                None => Err(Errors::AmbiguousProjectName(names))
+            }
+        },
+        result => result
+    }
+}
+
+/// Finds the project for a command that changes the project or moves its
+/// directory, where acting on another project than the user meant is worse
+/// than a typo. The name has to be exact, unless pile runs in a terminal and
+/// the user confirms (or picks) the project that `resolve_project` finds.
+fn resolve_exact_project(name: &str, conn: &Connection) -> Result<Project, Errors> {
+    if Project::name_taken(name, conn)? {
+        return Project::get_from_db_by_name(name, conn);
+    }
+    if !pick::is_interactive() {
+        return Err(Errors::ProjectDoesNotExist(name.to_string()));
+    }
+    match Project::find(name, conn) {
+        Ok(project) => {
+            let question = format!("There is no project called \"{}\", did you mean \"{}\"?", name, project.name);
+            if pick::confirm(&question) { Ok(project) } else { Err(Errors::NotConfirmed) }
+        },
+        // picking one of the matches is a choice of its own
+        Err(Errors::AmbiguousProjectName(_)) => resolve_project(name, conn),
+        Err(error) => Err(error),
+    }
+}
+
+/// Opens the full-screen terminal UI.
+pub fn tui_command(workspace: PathBuf, rules: &NameRules) -> Result<(), Errors> {
     let conn = get_connection(&workspace)?;
-    let project = Project::get_from_db_by_name(&name, &conn)?;
-    Ok(project.get_path(&workspace))
+    tui::run(workspace, conn, rules.clone())
+}
+
+/// Lets the user pick a project interactively and prints its path.
+/// `query` is the initial filter, it can be changed in the picker.
+pub fn pick_command(workspace: PathBuf, query: Option<String>) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let projects = without_archived(Project::fetch_from_db(&conn, None, None, None)?);
+    if projects.is_empty() {
+        eprintln!("No projects where found :(");
+        return Err(Errors::ExitStatus(1));
+    }
+
+    match pick::pick(&projects, &query.unwrap_or_default())? {
+        Some(index) => {
+            println!("{}", projects[index].get_path(&workspace).to_string_lossy());
+            Ok(())
+        },
+        // the same status as a shell gives a command stopped with ctrl-c
+        None => Err(Errors::ExitStatus(130))
+    }
+}
+
+/// Prints the shell integration script for the given shell,
+/// `cmd` is the name of the function that jumps into a project.
+/// `generated` are the static completions, see `completions_command`.
+pub fn init_command(shell: shell::Shell, cmd: String, generated: String) -> Result<(), Errors> {
+    let valid = !cmd.is_empty()
+        && cmd.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid || cmd == "pile" {
+        return Err(Errors::InvalidFunctionName);
+    }
+    let completions = shell::completion_script(shell, &generated);
+    print!("{}", shell::init_script(shell, &cmd, &completions));
+    Ok(())
+}
+
+/// Prints the completion script for the given shell. `generated` is
+/// the script with the static completions (subcommands and options),
+/// which is extended with project names and tags from the database.
+pub fn completions_command(shell: shell::Shell, generated: String) -> Result<(), Errors> {
+    print!("{}", shell::completion_script(shell, &generated));
+    Ok(())
+}
+
+/// Prints the path of a project, the shell function created by
+/// `pile init` replaces this command with an actual cd.
+pub fn cd_command(name: String, workspace: PathBuf) -> Result<(), Errors> {
+    let path = get_project_path(name, &workspace)?;
+    println!("{}", path.to_string_lossy());
+    eprintln!("Note: pile can not change the directory of your shell by itself,");
+    eprintln!("add the shell integration from \"pile init --help\" to use \"pile cd\"");
+    Ok(())
+}
+
+/// Prints the path of the project that matches `query` and has the highest
+/// frecency score, see the frecency module. Unlike `get_project_path`
+/// several matches are fine, the best match only breaks ties.
+pub fn jump_command(query: String, workspace: PathBuf) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let scores = frecency::scores(date::now(), &conn)?;
+    let best = without_archived(Project::fetch_from_db(&conn, None, None, None)?)
+        .into_iter()
+        .filter_map(|project| {
+            let matched = fuzzy::score(&query, &project.name)?;
+            let score = scores.get(&project.name).copied().unwrap_or_default();
+            Some((score, matched, project))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    let mut project = match best {
+        Some((_, _, project)) => project,
+        None => return Err(Errors::ProjectDoesNotExist(query)),
+    };
+    mark_opened(&mut project, &conn);
+    println!("{}", project.get_path(&workspace).to_string_lossy());
+    Ok(())
+}
+
+/// Prints the path of the project that was used before the last one,
+/// that becomes the last one so "pile -" goes back and forth between two.
+pub fn previous_command(workspace: PathBuf) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let name = frecency::previous(&conn)?.ok_or(Errors::NoPreviousProject)?;
+    let mut project = Project::get_from_db_by_name(&name, &conn)?;
+    mark_opened(&mut project, &conn);
+    println!("{}", project.get_path(&workspace).to_string_lossy());
+    Ok(())
+}
+
+/// Lists the projects that were used, the highest frecency score first.
+pub fn recent_command(workspace: PathBuf, limit: usize, format: Format) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let scores = frecency::scores(date::now(), &conn)?;
+    let projects = without_archived(Project::fetch_from_db(&conn, None, None, None)?);
+    let mut recent: Vec<frecency::RecentProject> = projects.iter()
+        .filter_map(|project| {
+            let score = scores.get(&project.name).copied().filter(|&score| score > 0.0)?;
+            Some(frecency::RecentProject { project, workspace: &workspace, score })
+        })
+        .collect();
+    recent.sort_by(|a, b| b.score.total_cmp(&a.score));
+    recent.truncate(limit);
+
+    if recent.is_empty() && format == Format::Table {
+        println!("No projects where used yet :(");
+        return Ok(());
+    }
+
+    print!("{}", output::render(&recent, &format)?);
+    Ok(())
+}
+
+/// Prints completion candidates, one per line. Used by the shell scripts,
+/// `words` are the words after "pile" up to and including the one being completed.
+/// Fails (silently) when the shell should use the static completions instead.
+pub fn complete_command(workspace: PathBuf, words: Vec<String>) -> Result<(), Errors> {
+    let kind = match complete::context(&words) {
+        Some(kind) => kind,
+        None => return Err(Errors::ExitStatus(1))
+    };
+    let conn = get_connection(&workspace)?;
+    for candidate in complete::candidates(kind, &conn, &workspace)? {
+        println!("{}", candidate);
+    }
+    Ok(())
 }
 
 /// Prints the path to a given project.
+/// The path is printed as is by default, other formats
+/// print the whole project (like the list command does).
 pub fn path_command(
     name: String,
     workspace: PathBuf,
     clipboard: bool,
-    execute: Option<Vec<String>>
+    execute: Option<Vec<String>>,
+    capture: bool,
+    format: Format
     ) -> Result<(), Errors> {
-    let path = get_project_path(name, &workspace)?;
+    let conn = get_connection(&workspace)?;
+    let mut project = resolve_project(&name, &conn)?;
+    mark_opened(&mut project, &conn);
+    let path = project.get_path(&workspace);
     let path_string = path.to_string_lossy();
-    println!("{}", path_string);
+    let projects = [project];
+    let records = output::project_records(&projects, &workspace, PlainField::Path, false);
+    print!("{}", output::render(&records, &format)?);
 
     if clipboard {
-        let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
-        ctx.set_contents(path_string.to_owned().to_string()).unwrap();
+        copy_to_clipboard(&path_string)?;
         println!("The path has been copied to the clipboard.");
     }
     // If the user specified a command, execute it.
     if let Some(args) = execute{
         if !args.is_empty() {
-            let output = execute_command(args, &path);
-            if output.is_err() {
-                println!("Failed to execute the command.");
-            } else if let Ok(value) = output {
-                io::stdout().write_all(&value.stdout).unwrap();
-                io::stderr().write_all(&value.stderr).unwrap();
+            let status = execute_command(args, &path, capture)
+                .map_err(|_| Errors::CouldNotExecute)?;
+            // Exit with the same status as the command
+            if !status.success() {
+                return Err(Errors::ExitStatus(status.code().unwrap_or(1)));
             }
         }
     }
@@ -148,196 +766,851 @@ pub fn path_command(
     Ok(())
 }
 
-/// Helper function that will execute one or more commands
-fn execute_command(args: Vec<String>, path: &PathBuf) -> Result<Output, io::Error> {
-    if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .current_dir(path)
-            .arg("/C")
-            .args(&args)
-            .output()
+/// Puts the text on the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<(), Errors> {
+    let mut ctx: ClipboardContext = ClipboardProvider::new()
+        .map_err(|_| Errors::ClipboardFailed)?;
+    ctx.set_contents(text.to_string()).map_err(|_| Errors::ClipboardFailed)
+}
+
+/// Creates a Command that runs a shell command line in the given directory,
+/// used for the commands that the user wrote in a config file.
+pub fn shell_command(script: &str, path: &Path) -> Command {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
     } else {
-        Command::new("sh")
-            .current_dir(path)
-            .arg("-c")
-            .args(&args)
-            .output()
-    }
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(script).current_dir(path);
+    command
 }
 
-/// Opens the path to a project in a file browser.
-pub fn open_project(name: String, workspace: PathBuf) -> Result<(), Errors> {
-    let path = get_project_path(name, &workspace)?;
-    open::that(path)?;
+/// Creates a Command that runs the program `args[0]` with the rest of
+/// the arguments as they are, without a shell that would split or
+/// expand them again. `args` must not be empty.
+pub fn program_command(args: &[String], path: &Path) -> Command {
+    let mut command = Command::new(&args[0]);
+    command.args(&args[1..]).current_dir(path);
+    command
+}
+
+/// Runs a command in every project that matches the filters and prints
+/// a summary. Fails if the command failed in any of the projects.
+#[allow(clippy::too_many_arguments)]
+pub fn foreach_command(
+    workspace: PathBuf,
+    name: Option<String>,
+    tag: Option<String>,
+    where_query: Option<String>,
+    jobs: usize,
+    keep_going: bool,
+    args: Vec<String>
+    ) -> Result<(), Errors> {
+
+    let where_query = parse_where(where_query)?;
+    let conn = get_connection(&workspace)?;
+    let projects: Vec<Project> = Project::fetch_from_db(&conn, name, tag, where_query.as_ref())?
+        .into_iter()
+        .filter(|project| !project.archived && project.get_path(&workspace).is_dir())
+        .collect();
+
+    if projects.is_empty() {
+        println!("No projects where found :(");
+        return Ok(());
+    }
+
+    let reports = foreach::run(&projects, &workspace, &args, jobs, keep_going);
+
+    let mut table = output::new_table(&["Project name", "Result", "Time"]);
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for report in reports.iter() {
+        let result = match &report.outcome {
+            foreach::Outcome::Success => String::from("ok"),
+            foreach::Outcome::Failed(Some(code)) => format!("failed (exit code {})", code),
+            foreach::Outcome::Failed(None) => String::from("failed (killed)"),
+            foreach::Outcome::Error(message) => format!("failed to start: {}", message),
+            foreach::Outcome::Skipped => String::from("skipped"),
+        };
+        match report.outcome {
+            foreach::Outcome::Success => passed += 1,
+            foreach::Outcome::Skipped => skipped += 1,
+            _ => failed += 1,
+        }
+        table.add_row(Row::new(vec![
+            Cell::new(&report.name),
+            Cell::new(&result),
+            Cell::new(&format!("{:.1}s", report.duration.as_secs_f64()))
+        ]));
+    }
+    println!();
+    table.printstd();
+    println!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+
+    if failed > 0 {
+        return Err(Errors::CommandFailed);
+    }
     Ok(())
 }
 
+/// Helper function that will execute a command, see `program_command`.
+/// The command shares stdin, stdout and stderr with pile, unless `capture`
+/// is true, then its output is printed after the command has exited.
+pub(crate) fn execute_command(args: Vec<String>, path: &Path, capture: bool) -> Result<ExitStatus, io::Error> {
+    let mut command = program_command(&args, path);
+    if !capture {
+        return command.status();
+    }
+    let output = command.output()?;
+    io::stdout().write_all(&output.stdout)?;
+    io::stderr().write_all(&output.stderr)?;
+    Ok(output.status)
+}
+
+/// Opens the path to a project in a file browser, or
+/// in an editor if an editor command is given.
+pub fn open_project(name: String, workspace: PathBuf, editor: Option<String>) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let mut project = resolve_project(&name, &conn)?;
+    hooks::run(&workspace, When::Pre, Event::Open, &project, &[])?;
+    mark_opened(&mut project, &conn);
+    let path = project.get_path(&workspace);
+    match editor {
+        Some(editor) => {
+            // the editor is run in the project, so the path never has to be quoted
+            let status = shell_command(&format!("{} .", editor), &path)
+                .status()
+                .map_err(|_| Errors::CouldNotExecute)?;
+            if !status.success() {
+                return Err(Errors::ExitStatus(status.code().unwrap_or(1)));
+            }
+        },
+        None => { open::that(&path)?; },
+    }
+    hooks::run(&workspace, When::Post, Event::Open, &project, &[])
+}
+
 pub fn edit(
     name: String,
     new_name: Option<String>,
     new_tags: Option<Vec<String>>,
-    workspace: PathBuf
+    workspace: PathBuf,
+    rules: &NameRules
     ) -> Result<(), Errors> {
     
     let conn = get_connection(&workspace)?;
-    let mut project = Project::get_from_db_by_name(&name, &conn)?;
+    let mut project = resolve_exact_project(&name, &conn)?;
 
     if let Some(name) = new_name {
-        let returned_name = project.edit_name(&name, &conn, &workspace)?;
+        let returned_name = rename_project(&mut project, &name, rules, &conn, &workspace)?;
         println!("The name has been changed to {}", returned_name);
     }
 
     if let Some(tags) = new_tags {
-        project.edit_tags(&tags, &conn)?;
-        println!("The tags has been changed to {}", tags.join(", "));
+        retag_project(&mut project, &tags, &conn, &workspace)?;
+        println!("The tags has been changed to {}", project.tags.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Renames a project, with the rename hooks run before and after.
+/// The hooks get the other name in PILE_NEW_NAME and PILE_OLD_NAME.
+/// The new name is cleaned up with the name rules first,
+/// and the cleaned new name is returned on Ok()
+pub fn rename_project(
+    project: &mut Project,
+    new_name: &str,
+    rules: &NameRules,
+    conn: &Connection,
+    workspace: &Path
+    ) -> Result<String, Errors> {
+
+    let cleaned_name = slug(new_name, rules)?;
+    hooks::run(workspace, When::Pre, Event::Rename, project, &[("PILE_NEW_NAME", cleaned_name.to_string())])?;
+    let old_name = project.name.clone();
+    let name = project.edit_name(&cleaned_name, conn, workspace)?;
+    hooks::run(workspace, When::Post, Event::Rename, project, &[("PILE_OLD_NAME", old_name)])?;
+    Ok(name)
+}
+
+/// Replaces the tags of a project, with the tags hooks run before and after.
+/// The hooks get the other tags in PILE_NEW_TAGS and PILE_OLD_TAGS.
+pub fn retag_project(
+    project: &mut Project,
+    new_tags: &[String],
+    conn: &Connection,
+    workspace: &Path
+    ) -> Result<(), Errors> {
+
+    let cleaned_tags = Project::clean_tags(new_tags).join(",");
+    hooks::run(workspace, When::Pre, Event::Tags, project, &[("PILE_NEW_TAGS", cleaned_tags)])?;
+    let old_tags = project.tags.join(",");
+    project.edit_tags(new_tags, conn)?;
+    hooks::run(workspace, When::Post, Event::Tags, project, &[("PILE_OLD_TAGS", old_tags)])
+}
+
+/// Prints the schema version of the database and applies
+/// any pending migrations (unless it is a dry run).
+pub fn migrate_command(workspace: PathBuf, dry_run: bool) -> Result<(), Errors> {
+    let mut conn = open_database(&workspace)?;
+    println!(
+        "Schema version: {} (latest: {})",
+        migrations::current_version(&conn)?,
+        migrations::latest_version()
+    );
+
+    let pending = migrations::pending(&conn)?;
+    if pending.is_empty() {
+        println!("The database is up to date");
+        return Ok(());
+    }
+
+    for migration in pending.iter() {
+        println!("  {:>3}: {}", migration.version, migration.description);
     }
 
+    if dry_run {
+        println!("{} pending migration(s), none were applied", pending.len());
+    } else {
+        let applied = migrations::migrate(&mut conn)?;
+        println!("{} migration(s) applied", applied.len());
+    }
     Ok(())
 }
 
+/// Opens the "pile.db" file in the workspace directory
+/// without applying any migrations.
+/// The file is created if it does not exist.
+pub fn open_database(workspace: &Path) -> Result<Connection, Errors> {
+    if !workspace.is_dir() {
+        return Err(Errors::DirDoesNotExist(workspace.to_path_buf()));
+    }
+    let filepath = workspace.join("pile.db");
+    let conn = Connection::open(&filepath)
+        .context(|| format!("could not open the database {}", filepath.to_string_lossy()))?;
+    // SQLite does not enforce foreign keys unless asked to
+    conn.execute_batch("PRAGMA foreign_keys = ON")
+        .context(|| format!("could not open the database {}", filepath.to_string_lossy()))?;
+    Ok(conn)
+}
+
+/// Runs `f` inside of a savepoint, all of the changes it made
+/// to the database are rolled back if it returns an error.
+fn in_savepoint<T>(
+    conn: &Connection,
+    f: impl FnOnce() -> Result<T, Errors>
+    ) -> Result<T, Errors> {
+    conn.execute_batch("SAVEPOINT pile")?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE pile")?;
+            Ok(value)
+        },
+        Err(error) => {
+            // the original error is more interesting than a failed rollback
+            let _ = conn.execute_batch("ROLLBACK TO pile; RELEASE pile");
+            Err(error)
+        }
+    }
+}
+
 /// Gets a connection to the database
 /// If a file named "pile.db" does not
 /// exist in the workspace directory, such a file will be created.
+/// Pending schema migrations are applied before the connection is returned.
 /// 
 /// # Example:
-/// ``` 
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use pile::get_connection;
+/// # let workspace = PathBuf::from("workspace");
 /// let conn = get_connection(&workspace)
-///     .expect("Failed to connect to the database");
-/// ``` 
-pub fn get_connection(workspace: &PathBuf) -> Result<Connection, rusqlite::Error> {
-    let mut filepath = workspace.clone();
-    filepath.push("pile.db");
-    let conn = Connection::open(filepath)?;
-    conn.execute(
-        "create table if not exists projects (
-             id integer primary key,
-             name text not null unique,
-             tags text
-         )",
-        NO_PARAMS,
-    )?;
+///     .unwrap_or_else(|_| panic!("Failed to connect to the database"));
+/// ```
+pub fn get_connection(workspace: &Path) -> Result<Connection, Errors> {
+    let mut conn = open_database(workspace)?;
+    migrations::migrate(&mut conn)?;
     Ok(conn)
 }
 
 /// Creates a new instance of a Project struct
 /// and adds it to the database, and creates a directory.
+/// `readme` is the text of the readme to create, if any,
+/// with the same variables as the templates.
 pub fn add_project(
     name: String,
     tags: Vec<String>,
     workspace:PathBuf,
     clone: Option<String>,
-    readme: bool
+    readme: Option<String>,
+    template: Option<String>,
+    rules: &NameRules
     ) -> Result<(), Errors> {
 
-    let project = Project::new(name, tags);
+    let project = Project::new(slug(&name, rules)?, tags);
     let conn = get_connection(&workspace)?;
 
-    if Project::name_taken(&project.name, &conn) {
-        return Err(Errors::ProjectNameTaken);
+    if let Some(taken) = Project::conflicting_name(&project.name, &conn)? {
+        return Err(Errors::ProjectNameTaken(taken));
     }
+    Project::validate_tags(&project.tags)?;
+    let template = match template {
+        Some(template) => Some(templates::template_dir(&workspace, &template)?),
+        None => None
+    };
 
-    project.create_directory(&workspace)?;
-    project.add_to_db(&conn)?;
-
-    if readme {
-        let mut readme_path = project.get_path(&workspace);
-        readme_path.push("README.md");
+    let mut project = project;
+    project.remote = clone;
 
-        let mut file = fs::File::create(&readme_path)?;
+    hooks::run(&workspace, When::Pre, Event::Add, &project, &[])?;
+    project.create_directory(&workspace)?;
 
-        let file_content = format!("# {}", &project.name);
-        file.write_all(file_content.as_bytes())?;
-    }
-    
-    if let Some(clone_url) = clone {
-        Command::new("git")
-             .current_dir(&project.get_path(&workspace))
-             .args(vec!["clone", &clone_url, "."])
-             .output()?;
+    // Remove the directory again if anything goes wrong, the
+    // changes to the database are rolled back by the savepoint.
+    let result = in_savepoint(&conn, || {
+        project.add_to_db(&conn)?;
+        fill_directory(&project, &workspace, &conn, readme.as_deref(), template.as_deref())
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_dir_all(project.get_path(&workspace));
+        return Err(error);
     }
     
     println!("Project created");
     println!("{}", project.get_path(&workspace).to_string_lossy());
-    Ok(())
+    hooks::run(&workspace, When::Post, Event::Add, &project, &[])
 }  
 
-#[derive(Debug)]
+/// Clones the remote of a newly created project, copies the
+/// template and creates a readme if it was asked for.
+/// The project has to be in the database already, since the
+/// conditions of the template are checked with queries.
+fn fill_directory(
+    project: &Project,
+    workspace: &Path,
+    conn: &Connection,
+    readme: Option<&str>,
+    template: Option<&Path>
+    ) -> Result<(), Errors> {
+
+    let path = project.get_path(workspace);
+
+    // Clone first, since git refuses to clone into a non-empty directory
+    if let Some(clone_url) = &project.remote {
+        let status = Command::new("git")
+             .current_dir(&path)
+             .args(vec!["clone", clone_url, "."])
+             .status()?;
+        if !status.success() {
+            return Err(Errors::CloneFailed);
+        }
+    }
+
+    let variables = templates::Variables::new(project, workspace);
+    if let Some(template) = template {
+        let mut excluded = Vec::new();
+        for (file, condition) in templates::conditions(template)? {
+            if !project.matches(&condition, conn)? {
+                excluded.push(file);
+            }
+        }
+        templates::apply(template, &path, &variables, &excluded)
+            .context(|| format!("could not copy the template {}", template.to_string_lossy()))?;
+    }
+
+    let readme_path = path.join("README.md");
+    if let Some(readme) = readme {
+        if !readme_path.exists() {
+            let mut file = fs::File::create(&readme_path)?;
+            file.write_all(variables.substitute(readme).as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the names of the project templates in the workspace.
+pub fn template_list_command(workspace: PathBuf) -> Result<(), Errors> {
+    let names = templates::list(&workspace)?;
+    if names.is_empty() {
+        println!("There are no templates, create one with \"pile template new <name>\"");
+        return Ok(());
+    }
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+/// Creates an (almost) empty template and prints its path.
+pub fn template_new_command(workspace: PathBuf, template: String) -> Result<(), Errors> {
+    let path = templates::create(&workspace, &template)?;
+    println!("Template created, add the files for new projects to:");
+    println!("{}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Creates a template from the files of an existing project.
+pub fn template_from_project_command(
+    workspace: PathBuf,
+    name: String,
+    template: String
+    ) -> Result<(), Errors> {
+
+    let conn = get_connection(&workspace)?;
+    let project = resolve_project(&name, &conn)?;
+    let path = templates::create_from_project(&workspace, &project, &template)?;
+    println!("Template created from {}", project.name);
+    println!("{}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Runs git fetch (or git pull if `pull` is true) in all of the selected
+/// git projects and prints a summary. Either a name, a tag or `all` is needed.
+pub fn fetch_command(
+    workspace: PathBuf,
+    name: Option<String>,
+    tag: Option<String>,
+    all: bool,
+    pull: bool
+    ) -> Result<(), Errors> {
+
+    let conn = get_connection(&workspace)?;
+    let projects = match (name, tag) {
+        (Some(name), _) => vec![resolve_project(&name, &conn)?],
+        (None, Some(tag)) => without_archived(Project::fetch_from_db(&conn, None, Some(tag), None)?),
+        (None, None) if all => without_archived(Project::fetch_from_db(&conn, None, None, None)?),
+        (None, None) => {
+            println!("Pick the projects to fetch with a name, --tag or --all");
+            return Ok(());
+        }
+    };
+
+    let mut table = output::new_table(&["Project name", "Remote", "Result"]);
+    let mut failed = 0;
+    for project in projects.iter() {
+        let path = project.get_path(&workspace);
+        if !git::is_repository(&path) {
+            continue;
+        }
+        let remote = project.remote.clone()
+            .or_else(|| git::remote_url(&path))
+            .unwrap_or_default();
+        let result = match git::fetch(&path, pull) {
+            Ok(()) => String::from("ok"),
+            Err(message) => {
+                failed += 1;
+                format!("failed: {}", message)
+            }
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&project.name),
+            Cell::new(&remote),
+            Cell::new(&result)
+        ]));
+    }
+
+    if table.is_empty() {
+        println!("None of the projects are git repositories");
+        return Ok(());
+    }
+    table.printstd();
+
+    if failed > 0 {
+        return Err(Errors::FetchFailed);
+    }
+    Ok(())
+}
+
+/// Registers directories that already exist in the workspace as projects.
+/// Without a name or `all`, the unregistered directories are only listed.
+/// The tags given are added to every imported project,
+/// together with the tags suggested from manifest files (unless `suggest` is false).
+pub fn import_command(
+    workspace: PathBuf,
+    name: Option<String>,
+    all: bool,
+    tags: Vec<String>,
+    suggest: bool,
+    rules: &NameRules
+    ) -> Result<(), Errors> {
+
+    let conn = get_connection(&workspace)?;
+    Project::validate_tags(&tags)?;
+
+    let names = match name {
+        Some(name) => {
+            // The directory name is used as is, since the directory is never moved
+            let name = rules.check(&name)
+                .map_err(|reason| Errors::InvalidProjectName(name, reason))?;
+            if let Some(taken) = Project::conflicting_name(name.as_str(), &conn)? {
+                return Err(Errors::ProjectNameTaken(taken));
+            }
+            if !workspace.join(&name).is_dir() {
+                return Err(Errors::DirDoesNotExist(workspace.join(&name)));
+            }
+            vec![name.into()]
+        },
+        None => {
+            let names = import::unregistered_directories(&workspace, &conn)?;
+            if names.is_empty() {
+                println!("There are no unregistered directories in the workspace");
+                return Ok(());
+            }
+            if !all {
+                print_import_candidates(&names, &workspace);
+                return Ok(());
+            }
+            names
+        }
+    };
+
+    for name in names {
+        let project_name = match rules.check(&name) {
+            Ok(project_name) => project_name,
+            Err(reason) => {
+                println!("Skipped {:?}, {}", name, reason);
+                continue;
+            }
+        };
+        if let Some(taken) = Project::conflicting_name(&name, &conn)? {
+            println!("Skipped {:?}, the name \"{}\" is already in use", name, taken);
+            continue;
+        }
+        let mut project_tags = tags.clone();
+        if suggest {
+            project_tags.extend(import::suggest_tags(&workspace.join(&name)));
+        }
+        let mut project = Project::new(project_name, project_tags);
+        project.remote = git::remote_url(&workspace.join(&name));
+
+        project.add_to_db(&conn)?;
+        println!("Imported {} [{}]", project.name, project.tags.join(", "));
+    }
+    Ok(())
+}
+
+/// Prints a table of directories that can be imported.
+fn print_import_candidates(names: &[String], workspace: &Path) {
+    let mut table = output::new_table(&["Directory", "Suggested tags"]);
+    for name in names.iter() {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&import::suggest_tags(&workspace.join(name)).join(", "))
+        ]));
+    }
+    table.printstd();
+    println!("Import one with \"pile import <name>\" or all of them with \"pile import --all\"");
+}
+
+/// Checks that the database and the workspace directory agree.
+/// The problems are only reported, unless `fix` is true.
+pub fn doctor_command(workspace: PathBuf, fix: bool, rules: &NameRules) -> Result<(), Errors> {
+    let conn = get_connection(&workspace)?;
+    let problems = doctor::check(&workspace, &conn, rules)?;
+
+    if problems.is_empty() {
+        println!("No problems were found");
+        return Ok(());
+    }
+
+    if !fix {
+        let mut table = output::new_table(&["Problem", "Fix"]);
+        for problem in problems.iter() {
+            table.add_row(Row::new(vec![
+                Cell::new(&problem.description()),
+                Cell::new(&problem.fix_description())
+            ]));
+        }
+        table.printstd();
+        println!("{} problem(s) found, run with --fix to fix them", problems.len());
+        return Ok(());
+    }
+
+    let (mut fixed, mut failed) = (0, 0);
+    for problem in problems.iter() {
+        match problem.fix(&workspace, &conn) {
+            Ok(true) => {
+                fixed += 1;
+                println!("Fixed: {} ({})", problem.description(), problem.fix_description());
+            },
+            Ok(false) => println!("Not fixed: {}", problem.description()),
+            Err(error) => {
+                failed += 1;
+                // the same message as for an error that stops pile, with the root cause
+                let reason = match error.source() {
+                    Some(_) => format!("{}: {}", error, error.root_cause()),
+                    None => error.to_string(),
+                };
+                println!("Failed to fix: {} ({})", problem.description(), reason);
+            },
+        }
+    }
+    println!("{} of {} problem(s) fixed", fixed, problems.len());
+    if failed > 0 {
+        // the failures have already been reported
+        return Err(Errors::ExitStatus(1));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
 pub struct Project {
     pub name: String,
     pub tags: Vec<String>,
+    /// The url the project was cloned from
+    pub remote: Option<String>,
+    /// Archived projects are hidden from most commands,
+    /// and their directory might be compressed (see the archive module)
+    pub archived: bool,
+    /// When the project was added, in seconds since 1970-01-01. The times
+    /// are unknown for projects added before pile recorded them.
+    pub created_at: Option<i64>,
+    /// When the name, the tags or the archived state last changed
+    pub updated_at: Option<i64>,
+    /// When the project was last opened, or its path was asked for
+    pub last_opened_at: Option<i64>,
 }
 
 impl Project {
     /// Creates a new Project
-    pub fn new(name: String, tags: Vec<String>) -> Self{
-        let cleaned_name = name.trim().replace(" ", "-");
+    pub fn new(name: ProjectName, tags: Vec<String>) -> Self{
         Project {
-            name: cleaned_name,
-            tags
+            name: name.into(),
+            tags: Project::clean_tags(&tags),
+            remote: None,
+            archived: false,
+            created_at: None,
+            updated_at: None,
+            last_opened_at: None,
         }
     }
 
-    pub fn get_path(&self, workspace: &PathBuf) -> PathBuf {
-        let mut path = workspace.clone();
-        path.push(&self.name);
-        path
+    /// Trims the tags and removes empty ones and duplicates.
+    pub fn clean_tags(tags: &[String]) -> Vec<String> {
+        let mut cleaned: Vec<String> = Vec::new();
+        for tag in tags.iter().map(|tag| tag.trim()) {
+            if !tag.is_empty() && !cleaned.iter().any(|t| t == tag) {
+                cleaned.push(tag.to_string());
+            }
+        }
+        cleaned
+    }
+
+    /// Tags may not contain commas, since they are
+    /// separated by commas on the command-line and in the output.
+    pub fn validate_tags(tags: &[String]) -> Result<(), Errors> {
+        if tags.iter().any(|tag| tag.contains(',')) {
+            return Err(Errors::InvalidTag);
+        }
+        Ok(())
+    }
+
+    /// Returns the tags of a project, in the order they were added.
+    fn tags_from_db(name: &str, conn: &Connection) -> Result<Vec<String>, Errors> {
+        let mut stmt = conn.prepare(
+            "SELECT tags.name
+            FROM project_tags
+            JOIN tags ON tags.id = project_tags.tag_id
+            JOIN projects ON projects.id = project_tags.project_id
+            WHERE projects.name = ?1
+            ORDER BY project_tags.rowid"
+        )?;
+        stmt.query_map(params![name], |row| row.get(0))
+            .and_then(|tags| tags.collect())
+            .context(|| format!("could not read the tags of \"{}\" from the database", name))
+    }
+
+    /// Replaces the tags of the project named `name` in the database.
+    fn set_tags_in_db(name: &str, tags: &[String], conn: &Connection) -> Result<(), Errors> {
+        conn.execute(
+            "DELETE FROM project_tags
+            WHERE project_id = (SELECT id FROM projects WHERE name = ?1)",
+            params![name]
+        )?;
+        for tag in tags {
+            conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])?;
+            conn.execute(
+                "INSERT OR IGNORE INTO project_tags (project_id, tag_id)
+                SELECT projects.id, tags.id FROM projects, tags
+                WHERE projects.name = ?1 AND tags.name = ?2",
+                params![name, tag]
+            )?;
+        }
+        Project::remove_unused_tags(conn)
+    }
+
+    /// Returns all of the tags that are in use, sorted by name.
+    pub fn all_tags(conn: &Connection) -> Result<Vec<String>, Errors> {
+        let mut stmt = conn.prepare("SELECT name FROM tags ORDER BY name COLLATE NOCASE ASC")?;
+        let tags = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+        Ok(tags.collect::<Result<Vec<String>, _>>()?)
+    }
+
+    /// Removes tags that no longer belong to any project.
+    fn remove_unused_tags(conn: &Connection) -> Result<(), Errors> {
+        conn.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM project_tags)",
+            NO_PARAMS
+        )?;
+        Ok(())
+    }
+
+    pub fn get_path(&self, workspace: &Path) -> PathBuf {
+        workspace.join(&self.name)
     }
 
-    /// Checks if a project name is already in use
-    /// TODO: Check if a conflicting directory exists.
-    pub fn name_taken(name: &str, conn: &Connection) -> bool {
-        let mut stmt = conn.prepare("SELECT id FROM projects WHERE name = ?1").unwrap();
-        stmt.exists(params![name]).expect("Could not SELECT from database")
+    /// Checks if a project in the database has exactly this name. Only the
+    /// database is looked at, conflicting directories are checked by the
+    /// commands that create or rename one.
+    pub fn name_taken(name: &str, conn: &Connection) -> Result<bool, Errors> {
+        conn.prepare("SELECT id FROM projects WHERE name = ?1")
+            .and_then(|mut stmt| stmt.exists(params![name]))
+            .context(|| format!("could not look up \"{}\" in the database", name))
     }
     
-    /// Returns a single Project based on the provided name
-    /// **TODO:** this function should return a Result instead of panic if it fails.
+    /// Returns the name of the project that has the same name when case is
+    /// ignored, if there is one. Such names would get the same directory
+    /// on a case-insensitive file system, so they can not both be used.
+    pub fn conflicting_name(name: &str, conn: &Connection) -> Result<Option<String>, Errors> {
+        let names: Vec<String> = conn.prepare("SELECT name FROM projects")
+            .and_then(|mut stmt| stmt.query_map(NO_PARAMS, |row| row.get(0))?.collect())
+            .context(|| format!("could not look up \"{}\" in the database", name))?;
+        let lowercase = name.to_lowercase();
+        Ok(names.into_iter().find(|other| other.to_lowercase() == lowercase))
+    }
+    
+    /// Returns a single Project based on the provided name. Names that
+    /// could point outside of the workspace are never looked up, so an
+    /// old project with such a name can not be used by accident.
     pub fn get_from_db_by_name(name:&str, conn: &Connection) -> Result<Project, Errors> {
-        let mut stmt = conn.prepare("SELECT tags FROM projects WHERE name = ?1")
-            .unwrap();
-        let mut db_output = stmt.query_map(params![name], |row| {
-            let tags_string: String = row.get(0)?;
-            Ok(Project {
-                name: String::from(name),
-                tags: tags_string
-                    .split(',')
-                    .map(|tag| tag.to_string())
-                    .filter(|tag| tag != "")
-                    .collect()
-            })
-        }).unwrap();
+        ProjectName::new(name).map_err(|reason| Errors::InvalidProjectName(name.to_string(), reason))?;
+        if !Project::name_taken(name, conn)? {
+            return Err(Errors::ProjectDoesNotExist(name.to_string()));
+        }
+
+        let mut project = conn.query_row(
+            "SELECT name, remote, archived, created_at, updated_at, last_opened_at
+            FROM projects WHERE name = ?1",
+            params![name],
+            Project::from_row
+        ).context(|| format!("could not read \"{}\" from the database", name))?;
+        project.tags = Project::tags_from_db(name, conn)?;
+        Ok(project)
+    }
+
+    /// Reads a project, without its tags, from a row with the columns
+    /// name, remote, archived, created_at, updated_at and last_opened_at.
+    fn from_row(row: &rusqlite::Row) -> Result<Project, rusqlite::Error> {
+        Ok(Project {
+            name: row.get(0)?,
+            tags: Vec::new(),
+            remote: row.get(1)?,
+            archived: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+            last_opened_at: row.get(5)?,
+        })
+    }
+
+    /// Finds the project that `query` refers to. An exact name always wins,
+    /// otherwise the names are matched by prefix, then as a part of the name
+    /// and last fuzzily, so "rtr" finds "rust-tracer". Only the best kind of
+    /// match is used, and it has to be a single project or the
+    /// matching names are returned in an `AmbiguousProjectName` error.
+    pub fn find(query: &str, conn: &Connection) -> Result<Project, Errors> {
+        if Project::name_taken(query, conn)? {
+            return Project::get_from_db_by_name(query, conn);
+        }
 
-        match db_output.nth(0) {
-            Some(project) => Ok(project.unwrap()),
-            None => Err(Errors::CouldNotGetProject)
+        let mut projects = Project::fetch_from_db(conn, None, None, None)?;
+        let names: Vec<&str> = projects.iter().map(|project| project.name.as_str()).collect();
+        let matches = fuzzy::best_matches(query, &names);
+        match matches.len() {
+            0 => Err(Errors::ProjectDoesNotExist(query.to_string())),
+            1 => Ok(projects.swap_remove(matches[0])),
+            _ => Err(Errors::AmbiguousProjectName(
+                matches.into_iter().map(|i| projects[i].name.clone()).collect()
+            ))
         }
     }
 
     /// Remove a project from the database (based on its name)
-    /// **This method is not completed yet.**
     pub fn remove_from_db_by_name(name: &str, conn: &Connection) -> Result<(), Errors>{
-        let mut stmt = conn.prepare("DELETE FROM projects WHERE name = ?1").unwrap();
-        match stmt.execute(params![name]) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(Errors::FailedToRemoveProject)
-        }
+        conn.execute("DELETE FROM projects WHERE name = ?1", params![name])
+            .map_err(Errors::from)
+            .and_then(|_| Project::remove_unused_tags(conn))
+            .context(|| format!("could not remove \"{}\" from the database", name))
     }
 
-    /// Edits the name of a project, the cleaned new name is returned on Ok()
-    pub fn edit_name(&mut self, new_name: &str, conn:&Connection, workspace: &PathBuf) -> Result<String, Errors>{
-        let cleaned_name = new_name.trim().replace(" ", "-");
+    /// Edits the name of a project, the new name is returned on Ok()
+    ///
+    /// The new name is checked before anything is changed. The directory (or
+    /// the compressed archive) is moved inside the database transaction, so
+    /// if either fails both are left as they were. Afterwards the references
+    /// to the old path are updated, see the rename module.
+    pub fn edit_name(&mut self, new_name: &ProjectName, conn:&Connection, workspace: &Path) -> Result<String, Errors>{
+        let cleaned_name = new_name.to_string();
+        if cleaned_name == self.name {
+            return Ok(cleaned_name);
+        }
+        // a change of case is fine, but not to another project's name
+        match Project::conflicting_name(&cleaned_name, conn)? {
+            Some(taken) if taken != self.name => return Err(Errors::ProjectNameTaken(taken)),
+            _ => {},
+        }
 
-        let mut stmt = conn.prepare(
-            "UPDATE projects SET name = ?1 WHERE name = ?2"
-        ).unwrap();
-        stmt.execute(params![cleaned_name, self.name])?;
+        let old_path = self.get_path(workspace);
+        let new_path = workspace.join(&cleaned_name);
+        let (from, to) = if archive::is_compressed(workspace, self) {
+            (archive::compressed_path(workspace, &self.name), archive::compressed_path(workspace, &cleaned_name))
+        } else {
+            (old_path.clone(), new_path.clone())
+        };
+        if !from.exists() {
+            return Err(Errors::DirDoesNotExist(from));
+        }
+        // on a case-insensitive file system a change of case is the same directory
+        if to.exists() && !workspaces::same_directory(&from, &to) {
+            return Err(Errors::DirAlreadyExists(to));
+        }
 
-        let mut new_path = workspace.clone();
-        new_path.push(&cleaned_name);
+        let now = date::now();
+        let mut moved = false;
+        let result = in_savepoint(conn, || {
+            conn.execute(
+                "UPDATE projects SET name = ?1, updated_at = ?2 WHERE name = ?3",
+                params![cleaned_name, now, self.name]
+            ).context(|| format!("could not rename \"{}\" in the database", self.name))?;
+            let remotes = rename::update_remotes_in_db(conn, &old_path, &new_path)?;
 
-        fs::rename(self.get_path(&workspace), &new_path)?;
+            fs::rename(&from, &to).context(|| format!(
+                "could not rename {} to {}",
+                from.to_string_lossy(),
+                to.to_string_lossy()
+            ))?;
+            moved = true;
+            Ok(remotes)
+        });
+        let remotes = match result {
+            Ok(remotes) => remotes,
+            Err(error) => {
+                // the database was rolled back, so the directory is moved back too
+                if moved {
+                    let _ = fs::rename(&to, &from);
+                }
+                return Err(error);
+            }
+        };
 
-        self.name = cleaned_name.clone();
+        let old_name = std::mem::replace(&mut self.name, cleaned_name.clone());
+        self.updated_at = Some(now);
+        for warning in rename::update_references(workspace, &old_name, &cleaned_name, &remotes) {
+            eprintln!("Warning: {}", warning);
+        }
 
         Ok(cleaned_name)
     }
@@ -348,144 +1621,151 @@ impl Project {
         conn:&Connection,
     ) -> Result<(), Errors> {
 
-        let mut stmt = conn.prepare(
-            "UPDATE projects SET tags = ?1 WHERE name = ?2"
-        ).unwrap();
+        let new_tags = Project::clean_tags(new_tags);
+        Project::validate_tags(&new_tags)?;
 
-        stmt.execute(params![&new_tags.join(","), self.name])?;
+        let now = date::now();
+        in_savepoint(conn, || {
+            Project::set_tags_in_db(&self.name, &new_tags, conn)?;
+            conn.execute("UPDATE projects SET updated_at = ?1 WHERE name = ?2", params![now, self.name])?;
+            Ok(())
+        }).context(|| format!("could not change the tags of \"{}\" in the database", self.name))?;
 
-        self.tags = new_tags.to_owned();
+        self.tags = new_tags;
+        self.updated_at = Some(now);
         Ok(())
     }
 
     /// Get multiple projects from the database.
     /// The name_query and tag_query is used to filter out results
-    /// based on project name or a subject tag name.
+    /// based on a part of the project name or an exact subject tag name,
+    /// and where_query can be any query expression (see the query module).
     /// # Example:
-    /// ``` 
-    /// let projects = Project::fetch_from_db(&conn, None, Some(String::from("python"))).unwrap();
-    /// ``` 
+    /// ```no_run
+    /// # use std::path::PathBuf;
+    /// # use pile::{get_connection, Project};
+    /// # let conn = get_connection(&PathBuf::from("workspace")).ok().unwrap();
+    /// let projects = Project::fetch_from_db(&conn, None, Some(String::from("python")), None).ok().unwrap();
+    /// ```
     pub fn fetch_from_db(
         conn: &Connection,
         name_query: Option<String>,
-        tag_query: Option<String>
+        tag_query: Option<String>,
+        where_query: Option<&Expr>
         ) -> Result<Vec<Project>, Errors> {
-        
-        enum FilterBy {
-            NameAndTag {name: String, tag: String},
-            Name{name: String},
-            Tag{tag: String},
-            None
-        }
-        // Prepere the correct sqlite query:
-        let (mut stmt, filter_by) = match (name_query, tag_query) {
-
-            // If the user wants to filter by both project name and tag
-            (Some(name), Some(tag)) => 
-                (
-                    conn.prepare(
-                        "SELECT tags, name
-                        FROM projects
-                        WHERE name LIKE ?1
-                        AND tags LIKE ?2
-                        ORDER BY name COLLATE NOCASE ASC"
-                    ).unwrap(),
-                    FilterBy::NameAndTag {name, tag}
-                ),
 
-            // If the user wants to filter by project name
-            (Some(name), None) => 
-                (
-                    conn.prepare(
-                        "SELECT tags, name
-                        FROM projects
-                        WHERE name LIKE ?1
-                        ORDER BY name COLLATE NOCASE ASC"
-                    ).unwrap(),
-                    //params!(format!("%{}%", name))
-                    FilterBy::Name{name}
-                ),
+        // Build the WHERE clause from the filters that were given
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<String> = Vec::new();
 
-            // If the user wants to filter by tag
-            (None, Some(tag)) => 
-                (
-                    conn.prepare(
-                        "SELECT tags, name
-                        FROM projects
-                        WHERE tags LIKE ?1
-                        ORDER BY name COLLATE NOCASE ASC"
-                    ).unwrap(),
-                    //params!(format!("%{}%", tags))
-                    FilterBy::Tag {tag}
-                ),
+        // If the user wants to filter by project name
+        if let Some(name) = name_query {
+            conditions.push(String::from("projects.name LIKE ?"));
+            params.push(format!("%{}%", name));
+        }
 
-            // If the user does not want to filter = get everything
-            _ => 
-                (
-                    conn.prepare(
-                        "SELECT tags, name
-                        FROM projects
-                        ORDER BY name COLLATE NOCASE ASC"
-                    ).unwrap(),
-                    FilterBy::None
-                )
-        };
+        // If the user wants to filter by tag
+        if let Some(tag) = tag_query {
+            conditions.push(String::from(
+                "projects.id IN (SELECT project_id FROM project_tags
+                JOIN tags ON tags.id = project_tags.tag_id
+                WHERE tags.name = ?)"
+            ));
+            params.push(tag);
+        }
 
-        // This closure turns a rusqlite row into an actual Project struct,
-        let from_row_to_project = |row: &rusqlite::Row| -> rusqlite::Result<Project>{
-            let tags_string: String = row.get(0)?;
-            Ok(Project {
-                name: row.get(1)?,
-                tags: tags_string
-                    .split(',')
-                    .map(|tag| tag.to_string())
-                    .filter(|tag| tag != "")
-                    .collect()
-            })
+        // If the user wants to filter by a query expression
+        if let Some(expr) = where_query {
+            conditions.push(expr.to_sql(&mut params));
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
         };
 
-        // Get the actual projects from the database
-        let db_output = match filter_by {
-            FilterBy::None => 
-                stmt.query_map(NO_PARAMS, from_row_to_project),
+        let mut stmt = conn.prepare(&format!(
+            "SELECT projects.name, projects.remote, projects.archived,
+                projects.created_at, projects.updated_at, projects.last_opened_at
+            FROM projects
+            {}
+            ORDER BY projects.name COLLATE NOCASE ASC",
+            where_clause
+        )).context(|| "could not read the projects from the database")?;
 
-            FilterBy::NameAndTag {name, tag} => 
-                stmt.query_map(
-                    params![format!("%{}%", name), format!("%{}%", tag)],
-                    from_row_to_project
-                ),
+        let projects: Vec<Project> = stmt
+            .query_map(&params, Project::from_row)
+            .and_then(|rows| rows.collect())
+            .context(|| "could not read the projects from the database")?;
 
-            FilterBy::Name {name} => 
-                stmt.query_map(
-                    params![format!("%{}%", name)],
-                    from_row_to_project
-                ),
+        // Attach the tags to each of the projects
+        projects.into_iter()
+            .map(|mut project| {
+                project.tags = Project::tags_from_db(&project.name, conn)?;
+                Ok(project)
+            })
+            .collect()
+    }
 
-            FilterBy::Tag {tag} => 
-                stmt.query_map(
-                    params![format!("%{}%", tag)],
-                    from_row_to_project
-            )
-        };
+    /// Marks the project as archived, or not archived, in the database.
+    pub fn set_archived(&mut self, archived: bool, conn: &Connection) -> Result<(), Errors> {
+        let now = date::now();
+        conn.execute(
+            "UPDATE projects SET archived = ?1, updated_at = ?2 WHERE name = ?3",
+            params![archived, now, self.name]
+        ).context(|| format!("could not update \"{}\" in the database", self.name))?;
+        self.archived = archived;
+        self.updated_at = Some(now);
+        Ok(())
+    }
 
-        match db_output {
-            Err(_) => Err(Errors::CouldNotGetProject),
-            Ok(projects) => Ok(projects.map(|project| project.unwrap()).collect())
-        }
+    /// Records that the project was opened (or its path was asked for) now,
+    /// which also counts as a visit for its frecency, see the frecency module.
+    pub fn mark_opened(&mut self, conn: &Connection) -> Result<(), Errors> {
+        let now = date::now();
+        in_savepoint(conn, || frecency::record_visit(&self.name, now, conn))?;
+        self.last_opened_at = Some(now);
+        Ok(())
+    }
+
+    /// Checks if the project (which has to be in the database) matches a query.
+    pub fn matches(&self, expr: &Expr, conn: &Connection) -> Result<bool, Errors> {
+        let mut params = vec![self.name.clone()];
+        let condition = expr.to_sql(&mut params);
+        let sql = format!(
+            "SELECT EXISTS (SELECT 1 FROM projects WHERE projects.name = ? AND {})",
+            condition
+        );
+        Ok(conn.query_row(&sql, &params, |row| row.get(0))?)
     }
 
     /// Adds the project itself to a database using the given Connection.
-    pub fn add_to_db(&self, conn: &Connection) -> Result<(), rusqlite::Error> {
-        conn.execute(
-            "INSERT INTO projects (name, tags) VALUES (?1, ?2)",
-            params![self.name, self.tags.join(",")]
-        )?;
-        Ok(())
+    /// The times that are not known yet (as for a new project) are set to now.
+    pub fn add_to_db(&self, conn: &Connection) -> Result<(), Errors> {
+        Project::validate_tags(&self.tags)?;
+        let now = date::now();
+        in_savepoint(conn, || {
+            conn.execute(
+                "INSERT INTO projects (name, remote, archived, created_at, updated_at, last_opened_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    self.name,
+                    self.remote,
+                    self.archived,
+                    self.created_at.unwrap_or(now),
+                    self.updated_at.unwrap_or(now),
+                    self.last_opened_at
+                ]
+            )?;
+            Project::set_tags_in_db(&self.name, &self.tags, conn)
+        }).context(|| format!("could not add \"{}\" to the database", self.name))
     }
 
     /// Create a directory for the project.
-    pub fn create_directory(&self, workspace: &PathBuf) -> std::io::Result<()>{
-        fs::create_dir(&self.get_path(&workspace))?;
-        Ok(())
+    pub fn create_directory(&self, workspace: &Path) -> Result<(), Errors> {
+        let path = self.get_path(workspace);
+        fs::create_dir(&path)
+            .context(|| format!("could not create the directory {}", path.to_string_lossy()))
     }
 }
\ No newline at end of file
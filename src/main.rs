@@ -1,7 +1,13 @@
 use std::path::PathBuf;
+use std::error::Error;
 use std::process::exit;
-use pile::Errors;
+use pile::{Errors, RemoveDirectory};
+use pile::config::Config;
+use pile::output::Format;
+use pile::shell::Shell;
+use pile::sort::{Order, SortKey};
 use structopt::StructOpt;
+use structopt::clap;
 
 
 ///Pile – organize your projects from the command-line.
@@ -9,6 +15,22 @@ use structopt::StructOpt;
 ///Created by Eli Adelhult, and licensed under the MIT license.
 #[derive(StructOpt, Debug)]
 #[structopt(name = "Pile")]
+struct Opt {
+    /// The directory with the projects, instead of PILE_WORKSPACE or the
+    /// workspace in ~/.config/pile/config.toml
+    #[structopt(long, global = true, env = "PILE_WORKSPACE", hide_env_values = true, parse(from_os_str))]
+    workspace: Option<PathBuf>,
+    /// Use one of the workspaces from the config, see "pile workspaces"
+    #[structopt(long, short = "w", global = true, value_name = "NAME")]
+    workspace_name: Option<String>,
+    /// Print the whole chain of causes when something goes wrong
+    #[structopt(long, short, global = true)]
+    verbose: bool,
+    #[structopt(subcommand)]
+    command: Cli,
+}
+
+#[derive(StructOpt, Debug)]
 enum Cli {
     /// Open the documentation in a web browser.
     Doc,
@@ -17,14 +39,15 @@ enum Cli {
     Add {
         #[structopt()]
         name: String,
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf,
         /// Clone with git
         #[structopt(long, short)]
         clone: Option<String>,
-        /// Generate a readme
+        /// Generate a readme (from readme_template in the config, if there is one)
         #[structopt(long, short)]
         readme: bool,
+        /// Create the project from a template, see "pile template"
+        #[structopt(long, short = "T")]
+        template: Option<String>,
         #[structopt(
             multiple=true,
             value_name="subject tags"
@@ -40,14 +63,101 @@ enum Cli {
         /// Filter by tag name
         #[structopt(long, short)]
         tag: Option<String>,
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf
+        /// Filter with a query, e.g. 'rust and (cli or tui) and not name:old-*'
+        #[structopt(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Only list the archived projects
+        #[structopt(long, conflicts_with = "all")]
+        archived: bool,
+        /// List the archived projects as well
+        #[structopt(long, short)]
+        all: bool,
+        /// List the projects in all of the workspaces from the config
+        #[structopt(long)]
+        all_workspaces: bool,
+        /// Sort by name, created, updated, opened or size
+        #[structopt(long, value_name = "KEY", default_value = "name")]
+        sort: SortKey,
+        /// Reverse the order, e.g. the last opened first with "--sort opened --reverse"
+        #[structopt(long, short)]
+        reverse: bool,
+        /// Only list the first N projects
+        #[structopt(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Add when the projects were created, updated and last opened to the table
+        #[structopt(long, short)]
+        dates: bool,
+        /// Output format: table, json, csv, tsv, plain or a template like '{name}\t{path}'
+        #[structopt(long, short, env = "PILE_FORMAT")]
+        format: Option<Format>,
+    },
+
+    /// Show the git status of all projects
+    Status {
+        /// Filter by project name
+        #[structopt(long, short)]
+        name: Option<String>,
+        /// Filter by tag name
+        #[structopt(long, short)]
+        tag: Option<String>,
+        /// Filter with a query, e.g. 'rust and (cli or tui) and not name:old-*'
+        #[structopt(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Only show projects with uncommitted changes, unpushed commits or stashes
+        #[structopt(long, short)]
+        unsaved: bool,
+        /// Output format: table, json, csv, tsv, plain or a template like '{name}\t{branch}'
+        #[structopt(long, short, env = "PILE_FORMAT")]
+        format: Option<Format>,
+    },
+
+    /// Run a command in every project (that matches the filters)
+    Foreach {
+        /// Filter by project name
+        #[structopt(long, short)]
+        name: Option<String>,
+        /// Filter by tag name
+        #[structopt(long, short)]
+        tag: Option<String>,
+        /// Filter with a query, e.g. 'rust and (cli or tui) and not name:old-*'
+        #[structopt(long = "where", value_name = "QUERY")]
+        where_query: Option<String>,
+        /// Number of projects to run the command in at the same time
+        #[structopt(long, short, default_value = "1")]
+        jobs: usize,
+        /// Keep running the command in the rest of the projects after a failure
+        #[structopt(long, short)]
+        keep_going: bool,
+        /// The command to run, put it after "--". It is not run by a shell,
+        /// so use e.g. -- sh -c 'git pull && make' for pipes and the like
+        #[structopt(
+            required=true,
+            last=true,
+            value_name="COMMAND ARGS"
+        )]
+        command: Vec<String>
     },
 
     /// Open the workspace in a file manager
-    Workspace {
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf
+    Workspace,
+
+    /// List the named workspaces from the config, with their number of projects
+    ///
+    /// Add them to ~/.config/pile/config.toml:
+    ///     [workspaces]
+    ///     work = "~/work"
+    ///     personal = "~/projects"
+    /// and pick one with "pile -w work <command>".
+    #[structopt(verbatim_doc_comment)]
+    Workspaces,
+
+    /// Move a project (its directory and database row) to another workspace
+    Move {
+        #[structopt(value_name = "PROJECT NAME")]
+        name: String,
+        /// The name of a workspace from the config, or the path of a workspace
+        #[structopt(long, value_name = "WORKSPACE")]
+        to: String,
     },
 
     /// Print the path of a project directory
@@ -56,24 +166,43 @@ enum Cli {
             value_name="PROJECT NAME"
         )]  
         name: String,
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf,
         /// Execute a command in the project path
         #[structopt(
             long,
             short,
         )]
         clipboard: bool,
-        /// Execute a command in the project path
+        /// Execute a command in the project path (not run by a shell)
         #[structopt(
             long,
             short,
             multiple=true,
+            allow_hyphen_values=true,
             value_name="COMMAND ARGS"
         )]
-        execute: Option<Vec<String>>
+        execute: Option<Vec<String>>,
+        /// Print the output of the command after it has exited,
+        /// instead of connecting it to the terminal
+        #[structopt(long, requires = "execute")]
+        capture: bool,
+        /// Output format: plain, json, csv, tsv, table or a template like '{name}\t{tags}'
+        #[structopt(long, short, default_value = "plain")]
+        format: Format
+    },
+
+    /// Pick a project interactively and print its path
+    ///
+    /// Type to filter the projects by name and tags, move with the arrow
+    /// keys (or ctrl-p and ctrl-n) and press enter to select a project.
+    Pick {
+        /// The initial filter
+        #[structopt(value_name = "QUERY")]
+        query: Option<String>,
     },
 
+    /// Browse and manage the projects in a full-screen terminal UI
+    Tui,
+
     /// Edit the information about a project
     Edit {
         #[structopt(
@@ -91,105 +220,461 @@ enum Cli {
             value_name="NEW TAGS"
         )]
         new_tags: Option<Vec<String>>,
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace:PathBuf
     },
 
-    /// Open a project in a file manager
+    /// Open a project in a file manager (or an editor)
     Open {
         #[structopt(
             value_name="PROJECT NAME"
         )]  
         name: String, 
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf,
+        /// Open it in PILE_EDITOR, the editor from the config, VISUAL or EDITOR
+        #[structopt(long, short)]
+        editor: bool,
     },
 
-    /// Remove a project from the database
+    /// Remove a project from the database (and its directory, if asked to)
     Remove {
         #[structopt(
             value_name="PROJECT NAME"
         )]  
         name: String, 
-        #[structopt(long, env = "PILE_WORKSPACE", parse(from_os_str))]
-        workspace: PathBuf,
-    }
+        /// Delete the directory as well
+        #[structopt(long, conflicts_with_all = &["trash", "archive"])]
+        delete: bool,
+        /// Move the directory to the trash, it can be brought back with "pile restore"
+        #[structopt(long, conflicts_with = "archive")]
+        trash: bool,
+        /// Save the directory to a tarball in the archive, then delete it
+        #[structopt(long)]
+        archive: bool,
+        /// Do not ask before deleting the directory
+        #[structopt(long, short, requires = "delete")]
+        yes: bool,
+        /// Delete the directory even if git has uncommitted changes in it
+        #[structopt(long, requires = "delete")]
+        force: bool,
+    },
+
+    /// Archive a project, to hide it from "pile list" and friends
+    Archive {
+        #[structopt(value_name="PROJECT NAME")]
+        name: String,
+        /// Compress the directory to a .tar.zst in the .archive directory
+        #[structopt(long, short)]
+        compress: bool,
+    },
+
+    /// Unarchive a project, its directory is extracted if it was compressed
+    Unarchive {
+        #[structopt(value_name="PROJECT NAME")]
+        name: String,
+    },
+
+    /// Bring back a project that was removed with "pile remove --trash"
+    Restore {
+        /// The project name or trash id, the trash is listed if left out
+        #[structopt(value_name="PROJECT NAME")]
+        name: Option<String>,
+    },
+
+    /// Register directories in the workspace that are not in the database
+    Import {
+        /// The directory to import, the unregistered directories are listed if left out
+        #[structopt(
+            value_name="DIRECTORY NAME"
+        )]
+        name: Option<String>,
+        /// Import all of the unregistered directories
+        #[structopt(long, short, conflicts_with = "name")]
+        all: bool,
+        /// Do not add tags suggested from manifest files (Cargo.toml, package.json...)
+        #[structopt(long)]
+        no_suggest: bool,
+        /// Tags added to every imported project
+        #[structopt(
+            long,
+            short,
+            multiple=true,
+            value_name="subject tags"
+        )]
+        tags: Vec<String>,
+    },
+
+    /// Fetch (or pull) the git remotes of one or more projects
+    Fetch {
+        #[structopt(
+            value_name="PROJECT NAME"
+        )]
+        name: Option<String>,
+        /// Fetch all projects with this tag
+        #[structopt(long, short, conflicts_with = "name")]
+        tag: Option<String>,
+        /// Fetch all projects
+        #[structopt(long, short, conflicts_with_all = &["name", "tag"])]
+        all: bool,
+        /// Run git pull --ff-only instead of git fetch
+        #[structopt(long, short)]
+        pull: bool,
+    },
+
+    /// Check that the database and the workspace directory agree
+    #[structopt(alias = "fsck")]
+    Doctor {
+        /// Fix the problems that were found, instead of only reporting them
+        #[structopt(long)]
+        fix: bool,
+    },
+
+    /// Manage the templates that projects can be created from
+    ///
+    /// The templates are directories in the .templates directory of the workspace.
+    /// {{name}}, {{tags}}, {{date}} and {{author}} are replaced in the files and file names.
+    /// A .conditions file in a template can list files that are only created for
+    /// some projects, one per line like "src/cli.rs: tag:cli" (see "pile list --where").
+    #[structopt(verbatim_doc_comment)]
+    Template(TemplateCommand),
+
+    /// Print the shell integration (a function that jumps into projects)
+    ///
+    /// Add it to your shell with:
+    ///     bash: eval "$(pile init bash)" in ~/.bashrc
+    ///     zsh:  eval "$(pile init zsh)" in ~/.zshrc
+    ///     fish: pile init fish | source in ~/.config/fish/config.fish
+    #[structopt(verbatim_doc_comment)]
+    Init {
+        /// bash, zsh or fish
+        shell: Shell,
+        /// The name of the function that jumps into a project
+        #[structopt(long, default_value = "p")]
+        cmd: String,
+    },
+
+    /// Go to a project directory (needs the shell integration, see "pile init")
+    Cd {
+        #[structopt(
+            value_name="PROJECT NAME"
+        )]
+        name: String,
+    },
+
+    /// Go to the most used project that matches (needs the shell integration)
+    ///
+    /// Of the projects that match, even fuzzily, the one that was used
+    /// the most and the most recently wins, see "pile recent".
+    Jump {
+        #[structopt(value_name = "PARTIAL NAME")]
+        name: String,
+    },
+
+    /// Go back to the previously used project (needs the shell integration)
+    #[structopt(name = "-")]
+    Previous,
+
+    /// List the most used projects, by frecency
+    ///
+    /// Every time the path of a project is used (by cd, path, open or jump)
+    /// counts as a visit. The score is the number of visits, weighed by how
+    /// long ago the last visit was.
+    Recent {
+        /// Only list the first N projects
+        #[structopt(long, value_name = "N", default_value = "10")]
+        limit: usize,
+        /// Output format: table, json, csv, tsv, plain or a template like '{name}\t{score}'
+        #[structopt(long, short, env = "PILE_FORMAT")]
+        format: Option<Format>,
+    },
+
+    /// Print a completion script for pile
+    ///
+    /// The project names and tags are read from the database
+    /// when completing, so the script never has to be regenerated.
+    ///     bash: eval "$(pile completions bash)" in ~/.bashrc
+    ///     zsh:  eval "$(pile completions zsh)" in ~/.zshrc, after compinit
+    ///     fish: pile completions fish | source in ~/.config/fish/config.fish
+    /// "pile init" already includes the completions.
+    #[structopt(verbatim_doc_comment)]
+    Completions {
+        /// bash, zsh or fish
+        shell: Shell,
+    },
+
+
+    /// Upgrade the database to the latest schema version
+    Migrate {
+        /// Only list the pending migrations, do not apply them
+        #[structopt(long)]
+        dry_run: bool,
+    },
+
+    /// Any other command is looked up in the aliases from the config
+    #[structopt(external_subcommand)]
+    Alias(Vec<String>),
 }
 
-fn main() {
-    let user_input = Cli::from_args();
-    let result = match user_input {
+#[derive(StructOpt, Debug)]
+enum TemplateCommand {
+    /// List the templates
+    List,
+    /// Create a new template
+    New {
+        #[structopt(value_name = "TEMPLATE NAME")]
+        name: String,
+    },
+    /// Create a template from the files of a project
+    FromProject {
+        #[structopt(value_name = "PROJECT NAME")]
+        project: String,
+        #[structopt(value_name = "TEMPLATE NAME")]
+        name: String,
+    },
+}
+
+/// Generates the static completions (subcommands and options) for a shell.
+fn generate_completions(shell: Shell) -> String {
+    let clap_shell = match shell {
+        Shell::Bash => clap::Shell::Bash,
+        Shell::Zsh => clap::Shell::Zsh,
+        Shell::Fish => clap::Shell::Fish,
+    };
+    let mut script = Vec::new();
+    Opt::clap().gen_completions_to("pile", clap_shell, &mut script);
+    String::from_utf8_lossy(&script).to_string()
+}
+
+/// Runs a command. The workspace is the one from --workspace (or
+/// PILE_WORKSPACE), or else the one from the user's config file.
+/// Settings from the pile.toml in the workspace are added to the config.
+fn run(opt: Opt, user_config: &Config, expand_aliases: bool) -> Result<(), Errors> {
+    let Opt { workspace: cli_workspace, workspace_name, command, .. } = opt;
+    // a workspace picked by name wins over PILE_WORKSPACE
+    let cli_workspace = match &workspace_name {
+        Some(name) => Some(user_config.named_workspace(name)?),
+        None => cli_workspace,
+    };
+    let workspace = cli_workspace.clone().or_else(|| user_config.workspace.clone());
+    let config = match &workspace {
+        Some(workspace) if workspace.is_dir() => user_config.clone().with_workspace(workspace)?,
+        _ => user_config.clone(),
+    };
+    let workspace = || workspace.clone().ok_or(Errors::NoWorkspace);
+    let format_or_default = |format: Option<Format>| match format {
+        Some(format) => Ok(format),
+        None => config.format().map_err(Errors::InvalidConfig),
+    };
+
+    match command {
         Cli::Doc        => pile::open_documentation(),
         Cli::Edit {
             name,
             new_name,
-            new_tags,
-            workspace
-        }               => pile::edit(name, new_name, new_tags, workspace),
+            new_tags
+        }               => pile::edit(name, new_name, new_tags, workspace()?, &config.name_rules()),
         Cli::Open {
             name,
-            workspace
-        }               => pile::open_project(name, workspace),
+            editor
+        }               => {
+            let editor = if editor { Some(config.editor().ok_or(Errors::NoEditor)?) } else { None };
+            pile::open_project(name, workspace()?, editor)
+        },
         Cli::Path {
             name,
-            workspace,
             clipboard,
-            execute
-        }               => pile::path_command(name, workspace, clipboard, execute),
+            execute,
+            capture,
+            format
+        }               => pile::path_command(name, workspace()?, clipboard, execute, capture, format),
+        Cli::Pick {
+            query
+        }               => pile::pick_command(workspace()?, query),
+        Cli::Tui                  => pile::tui_command(workspace()?, &config.name_rules()),
         Cli::List {
-            workspace,
             name,
-            tag
-        }               => pile::print_list(workspace, name, tag),
-        Cli::Workspace {
-            workspace
-        }               => pile::open_workspace(workspace),
+            tag,
+            where_query,
+            archived,
+            all,
+            all_workspaces,
+            sort,
+            reverse,
+            limit,
+            dates,
+            format
+        }               => {
+            let order = Order { key: sort, reverse, limit };
+            let format = format_or_default(format)?;
+            if all_workspaces {
+                pile::print_list_all_workspaces(&config.workspaces, name, tag, where_query, archived, all, &order, dates, format)
+            } else {
+                pile::print_list(workspace()?, name, tag, where_query, archived, all, &order, dates, format)
+            }
+        },
+        Cli::Status {
+            name,
+            tag,
+            where_query,
+            unsaved,
+            format
+        }               => pile::status_command(workspace()?, name, tag, where_query, unsaved, format_or_default(format)?),
+        Cli::Foreach {
+            name,
+            tag,
+            where_query,
+            jobs,
+            keep_going,
+            command
+        }               => pile::foreach_command(workspace()?, name, tag, where_query, jobs, keep_going, command),
+        Cli::Workspace            => pile::open_workspace(workspace()?),
+        Cli::Workspaces           => pile::workspaces_command(&config.workspaces, workspace().ok().as_deref()),
+        Cli::Move {
+            name,
+            to
+        }               => {
+            let target = match config.named_workspace(&to) {
+                Err(Errors::UnknownWorkspace(_)) if to.contains(std::path::is_separator) => PathBuf::from(to),
+                target => target?,
+            };
+            pile::move_command(workspace()?, name, target)
+        },
         Cli::Remove {
             name,
-            workspace
-        }               => pile::remove_project(workspace, name),
+            delete,
+            trash,
+            archive,
+            yes,
+            force
+        }               => {
+            let directory = match (delete, trash, archive) {
+                (true, _, _) => RemoveDirectory::Delete,
+                (_, true, _) => RemoveDirectory::Trash,
+                (_, _, true) => RemoveDirectory::Archive,
+                _ => RemoveDirectory::Keep,
+            };
+            pile::remove_project(workspace()?, name, directory, yes, force)
+        },
+        Cli::Archive {
+            name,
+            compress
+        }               => pile::archive_command(workspace()?, name, compress),
+        Cli::Unarchive {
+            name
+        }               => pile::unarchive_command(workspace()?, name),
+        Cli::Restore {
+            name
+        }               => pile::restore_command(workspace()?, name),
         Cli::Add {
             name,
             tags,
-            workspace,
             clone,
-            readme
-        }               => pile::add_project(name, tags, workspace, clone, readme),
-    };
-
-    match result {
-        Ok(_) => (),
-        Err(Errors::ProjectNameTaken) => {
-            println!("Error: The project named is already in use");
-            exit(1);
-        },
-        Err(Errors::NotImplemented) => {
-            println!("Error: This feature is not implemented yet");
-            exit(1);
-        },
-        Err(Errors::DatabaseError) => {
-            println!("Error: a database error occurred");
-            exit(1);
-        },
-        Err(Errors::CouldNotGetProject) => {
-            println!("Error: could not get project(s)");
-            exit(1);
-        },
-        Err(Errors::FailedToRemoveProject) => {
-            println!("Error: could not remove the project from the db");
-            exit(1);
-        },
-        Err(Errors::ProjectDoesNotExist) => {
-            println!("Error: Such a project does not exist");
-            exit(1);
-        },
-        Err(Errors::IOError) => {
-            println!("Error: an IO error occurred");
-            exit(1);
+            readme,
+            template
+        }               => {
+            let tags = config.default_tags.iter().cloned().chain(tags).collect();
+            let readme = if readme { Some(config.readme_template()) } else { None };
+            pile::add_project(name, tags, workspace()?, clone, readme, template, &config.name_rules())
         },
-        Err(_) => {
-            println!("An error occured");
-            exit(1);
+        Cli::Template(TemplateCommand::List)=> pile::template_list_command(workspace()?),
+        Cli::Template(TemplateCommand::New {
+            name
+        })              => pile::template_new_command(workspace()?, name),
+        Cli::Template(TemplateCommand::FromProject {
+            project,
+            name
+        })              => pile::template_from_project_command(workspace()?, project, name),
+        Cli::Import {
+            name,
+            all,
+            no_suggest,
+            tags
+        }               => pile::import_command(workspace()?, name, all, tags, !no_suggest, &config.name_rules()),
+        Cli::Fetch {
+            name,
+            tag,
+            all,
+            pull
+        }               => pile::fetch_command(workspace()?, name, tag, all, pull),
+        Cli::Doctor {
+            fix
+        }               => pile::doctor_command(workspace()?, fix, &config.name_rules()),
+        Cli::Init {
+            shell,
+            cmd
+        }               => pile::init_command(shell, cmd, generate_completions(shell)),
+        Cli::Completions {
+            shell
+        }               => pile::completions_command(shell, generate_completions(shell)),
+        Cli::Cd {
+            name
+        }               => pile::cd_command(name, workspace()?),
+        Cli::Jump {
+            name
+        }               => pile::jump_command(name, workspace()?),
+        Cli::Previous             => pile::previous_command(workspace()?),
+        Cli::Recent {
+            limit,
+            format
+        }               => pile::recent_command(workspace()?, limit, format_or_default(format)?),
+        Cli::Migrate {
+            dry_run
+        }               => pile::migrate_command(workspace()?, dry_run),
+        Cli::Alias(args) => {
+            let name = args.first().cloned().unwrap_or_default();
+            let expanded = match config.expand_alias(&name) {
+                Some(expanded) if expand_aliases => expanded?,
+                _ => return Err(Errors::UnknownCommand(name)),
+            };
+            let words = std::iter::once(String::from("pile"))
+                .chain(expanded)
+                .chain(args.into_iter().skip(1));
+            let mut alias = Opt::from_iter_safe(words).unwrap_or_else(|error| error.exit());
+            if alias.workspace_name.is_none() {
+                alias.workspace = alias.workspace.or(cli_workspace);
+            }
+            // aliases can not refer to other aliases
+            run(alias, user_config, false)
         },
     }
 }
+
+fn main() {
+    // The hidden endpoint used by the completion scripts: "pile __complete -- <words>".
+    // It is not a part of Cli, since the completions generated by clap
+    // can not handle subcommands with names that start with "__".
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("__complete") {
+        let start = if args.get(2).map(String::as_str) == Some("--") { 3 } else { 2 };
+        let words = args[start..].to_vec();
+        let config = Config::load().unwrap_or_default();
+        let workspace = pile::complete::workspace_from_words(&words, &config)
+            .or_else(|| std::env::var_os("PILE_WORKSPACE").map(PathBuf::from))
+            .or(config.workspace);
+        let result = match workspace {
+            Some(workspace) => pile::complete_command(workspace, words),
+            None => Err(Errors::ExitStatus(1)),
+        };
+        exit(if result.is_ok() { 0 } else { 1 });
+    }
+
+    let opt = Opt::from_args();
+    let verbose = opt.verbose;
+    let result = Config::load().and_then(|config| run(opt, &config, true));
+    if let Err(error) = result {
+        // the command has already reported what went wrong
+        if let Errors::ExitStatus(code) = error {
+            exit(code);
+        }
+        if verbose {
+            eprintln!("Error: {}", error);
+            let mut source = error.source();
+            while let Some(cause) = source {
+                eprintln!("  caused by: {}", cause);
+                source = cause.source();
+            }
+        } else if error.source().is_some() {
+            eprintln!("Error: {}: {}", error, error.root_cause());
+        } else {
+            eprintln!("Error: {}", error);
+        }
+        exit(error.exit_code());
+    }
+}
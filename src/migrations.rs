@@ -0,0 +1,136 @@
+use rusqlite::{Connection, NO_PARAMS};
+use crate::{Context, Errors};
+
+/// A single step in the evolution of the pile.db schema.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// All the migrations, in the order they should be applied.
+/// The schema version of a database is stored in `PRAGMA user_version`,
+/// a database with version N has had the first N migrations applied.
+///
+/// **Never edit or reorder an existing migration**, add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the projects table",
+        // "if not exists" since databases created before the
+        // migrations were introduced already have this table.
+        sql: "CREATE TABLE IF NOT EXISTS projects (
+                 id integer primary key,
+                 name text not null unique,
+                 tags text
+             );",
+    },
+    Migration {
+        version: 2,
+        description: "move tags into a tags table and a project_tags join table",
+        sql: "ALTER TABLE projects RENAME TO projects_old;
+             CREATE TABLE projects (
+                 id integer primary key,
+                 name text not null unique
+             );
+             INSERT INTO projects (id, name) SELECT id, name FROM projects_old;
+
+             CREATE TABLE tags (
+                 id integer primary key,
+                 name text not null unique
+             );
+             CREATE TABLE project_tags (
+                 project_id integer not null references projects(id) on delete cascade,
+                 tag_id integer not null references tags(id) on delete cascade,
+                 primary key (project_id, tag_id)
+             );
+
+             -- Split the old comma separated tags column into one row per tag
+             CREATE TEMP TABLE split_tags AS
+             WITH RECURSIVE split(project_id, tag, rest) AS (
+                 SELECT id, '', tags || ',' FROM projects_old WHERE tags IS NOT NULL
+                 UNION ALL
+                 SELECT project_id,
+                        trim(substr(rest, 1, instr(rest, ',') - 1)),
+                        substr(rest, instr(rest, ',') + 1)
+                 FROM split WHERE rest <> ''
+             )
+             SELECT project_id, tag FROM split WHERE tag <> '';
+
+             INSERT OR IGNORE INTO tags (name) SELECT tag FROM split_tags;
+             INSERT OR IGNORE INTO project_tags (project_id, tag_id)
+                 SELECT split_tags.project_id, tags.id
+                 FROM split_tags JOIN tags ON tags.name = split_tags.tag;
+
+             DROP TABLE split_tags;
+             DROP TABLE projects_old;",
+    },
+    Migration {
+        version: 3,
+        description: "add the git remote of projects",
+        sql: "ALTER TABLE projects ADD COLUMN remote text;",
+    },
+    Migration {
+        version: 4,
+        description: "add the archived state of projects",
+        sql: "ALTER TABLE projects ADD COLUMN archived integer NOT NULL DEFAULT 0;",
+    },
+    Migration {
+        version: 5,
+        description: "add when projects were created, updated and last opened",
+        // In seconds since 1970-01-01, unknown (null) for the existing projects
+        sql: "ALTER TABLE projects ADD COLUMN created_at integer;
+             ALTER TABLE projects ADD COLUMN updated_at integer;
+             ALTER TABLE projects ADD COLUMN last_opened_at integer;",
+    },
+    Migration {
+        version: 6,
+        description: "add how often and in which order projects were visited",
+        // The visits are aged (scaled down) as they add up, see the frecency
+        // module, and last_visit numbers the visits in the order they happened
+        sql: "ALTER TABLE projects ADD COLUMN visits real NOT NULL DEFAULT 0;
+             ALTER TABLE projects ADD COLUMN last_visit integer;",
+    },
+];
+
+/// The schema version that this version of pile expects.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Returns the schema version of the database.
+pub fn current_version(conn: &Connection) -> Result<u32, Errors> {
+    let version: i64 = conn.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))?;
+    Ok(version as u32)
+}
+
+/// Returns the migrations that have not yet been applied to the database.
+pub fn pending(conn: &Connection) -> Result<Vec<&'static Migration>, Errors> {
+    let version = current_version(conn)?;
+    if version > latest_version() {
+        return Err(Errors::UnknownSchemaVersion);
+    }
+    Ok(MIGRATIONS.iter().filter(|m| m.version > version).collect())
+}
+
+/// Applies all the pending migrations, each one in its own transaction,
+/// and returns the migrations that were applied.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<&'static Migration>, Errors> {
+    let pending = pending(conn)?;
+    for migration in pending.iter() {
+        apply(conn, migration).context(|| format!(
+            "could not apply migration {} ({})",
+            migration.version,
+            migration.description
+        ))?;
+    }
+    Ok(pending)
+}
+
+/// Applies a single migration in a transaction.
+fn apply(conn: &mut Connection, migration: &Migration) -> Result<(), rusqlite::Error> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(migration.sql)?;
+    tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+    tx.commit()
+}
@@ -0,0 +1,188 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+use serde::Deserialize;
+
+/// The longest file name most file systems allow, in bytes.
+const MAX_LENGTH: usize = 255;
+
+/// A project name that is safe to use as the name of its directory:
+/// it can not point outside of the workspace or to one of the hidden
+/// directories of pile, like ".trash".
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProjectName(String);
+
+impl ProjectName {
+    /// Checks a name as it is, see `NameRules::slug` for
+    /// cleaning up a name that the user typed first.
+    pub fn new(name: &str) -> Result<ProjectName, InvalidName> {
+        if name.is_empty() {
+            return Err(InvalidName::Empty);
+        }
+        if name.starts_with('.') {
+            return Err(InvalidName::Hidden);
+        }
+        if name.contains('/') || name.contains('\\') {
+            return Err(InvalidName::PathSeparator);
+        }
+        if name.chars().any(char::is_control) {
+            return Err(InvalidName::ControlCharacter);
+        }
+        if name.len() > MAX_LENGTH {
+            return Err(InvalidName::TooLong);
+        }
+        Ok(ProjectName(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ProjectName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for ProjectName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ProjectName {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
+impl From<ProjectName> for String {
+    fn from(name: ProjectName) -> String {
+        name.0
+    }
+}
+
+/// Why a project name was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidName {
+    Empty,
+    /// Names starting with a dot, including "." and "..", are hidden
+    /// and the ones pile uses for itself
+    Hidden,
+    PathSeparator,
+    /// Control characters, like a NUL byte or a newline
+    ControlCharacter,
+    TooLong,
+    /// The name rules (see `NameRules`) would change the name to this one
+    NotCleanedUp(String),
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidName::Empty => write!(f, "it is empty"),
+            InvalidName::Hidden => write!(f, "it may not start with a dot"),
+            InvalidName::PathSeparator => write!(f, "it may not contain / or \\"),
+            InvalidName::ControlCharacter => write!(f, "it may not contain control characters"),
+            InvalidName::TooLong => write!(f, "it is longer than {} bytes", MAX_LENGTH),
+            InvalidName::NotCleanedUp(slug) => write!(f, "it does not follow the name rules, it would be \"{}\"", slug),
+        }
+    }
+}
+
+/// How the names that the user types are turned into project names,
+/// set in the `[names]` table of the config:
+///
+/// ```toml
+/// [names]
+/// lowercase = true
+/// transliterate = true
+/// allowed = "a-z0-9._-"
+/// ```
+///
+/// Spaces are always replaced with "-".
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NameRules {
+    /// Lowercases the name
+    pub lowercase: bool,
+    /// Replaces letters that are not ASCII with the closest ASCII,
+    /// so "Café Ünïcode" becomes "Cafe-Unicode"
+    pub transliterate: bool,
+    /// The characters that a name may contain, the others are replaced
+    /// with a single "-" (or left out if "-" is not allowed either)
+    pub allowed: Option<CharSet>,
+}
+
+impl NameRules {
+    /// Cleans up a name with the rules and checks the result.
+    pub fn slug(&self, name: &str) -> Result<ProjectName, InvalidName> {
+        let mut name = name.trim().replace(' ', "-");
+        if self.transliterate {
+            name = deunicode::deunicode(&name);
+        }
+        if self.lowercase {
+            name = name.to_lowercase();
+        }
+        if let Some(allowed) = &self.allowed {
+            let replace = allowed.contains('-');
+            let mut cleaned = String::new();
+            for c in name.chars() {
+                if c != '-' && allowed.contains(c) {
+                    cleaned.push(c);
+                } else if replace && !cleaned.is_empty() && !cleaned.ends_with('-') {
+                    cleaned.push('-');
+                }
+            }
+            name = cleaned.trim_end_matches('-').to_string();
+        }
+        ProjectName::new(&name)
+    }
+
+    /// Checks a name that has to be used as it is, like the name of a
+    /// directory that is imported: it has to be its own slug.
+    pub fn check(&self, name: &str) -> Result<ProjectName, InvalidName> {
+        let slug = self.slug(name)?;
+        if slug.as_str() != name {
+            return Err(InvalidName::NotCleanedUp(slug.into()));
+        }
+        Ok(slug)
+    }
+}
+
+/// A set of characters, written like "a-z0-9._-". A "-" at the
+/// start or the end is the character itself instead of a range.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CharSet(Vec<(char, char)>);
+
+impl CharSet {
+    pub fn contains(&self, c: char) -> bool {
+        self.0.iter().any(|&(start, end)| start <= c && c <= end)
+    }
+}
+
+impl TryFrom<String> for CharSet {
+    type Error = String;
+
+    fn try_from(set: String) -> Result<Self, Self::Error> {
+        let chars: Vec<char> = set.chars().collect();
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if i + 2 < chars.len() && chars[i + 1] == '-' {
+                let (start, end) = (chars[i], chars[i + 2]);
+                if start > end {
+                    return Err(format!("the range {}-{} is backwards", start, end));
+                }
+                ranges.push((start, end));
+                i += 3;
+            } else {
+                ranges.push((chars[i], chars[i]));
+                i += 1;
+            }
+        }
+        Ok(CharSet(ranges))
+    }
+}
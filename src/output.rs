@@ -0,0 +1,425 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use prettytable::{Table, Row, Cell};
+use prettytable::format;
+use serde_json::{json, Value};
+use crate::{date, Errors, Project};
+
+/// The ways a list of projects can be printed.
+/// See the "Output formats" section in the README for the details.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    /// A human readable table
+    Table,
+    /// A JSON array with one object per project
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+    /// Tab separated values with a header row
+    Tsv,
+    /// Only the name or path, one per line
+    Plain,
+    /// A user template such as `{name}\t{path}`
+    Template(String),
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "plain" => Ok(Format::Plain),
+            template if template.contains('{') => {
+                // the fields are checked when the template is used,
+                // since they depend on what is printed
+                if template.matches('{').count() != template.matches('}').count() {
+                    return Err(format!("unclosed \"{{\" in the template \"{}\"", template));
+                }
+                Ok(Format::Template(template.to_string()))
+            },
+            _ => Err(format!(
+                "unknown format \"{}\", expected table, json, csv, tsv, plain or a template like '{{name}}\\t{{path}}'",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Table => write!(f, "table"),
+            Format::Json => write!(f, "json"),
+            Format::Csv => write!(f, "csv"),
+            Format::Tsv => write!(f, "tsv"),
+            Format::Plain => write!(f, "plain"),
+            Format::Template(template) => write!(f, "{}", template),
+        }
+    }
+}
+
+/// What the plain format prints for each project.
+#[derive(Clone, Copy)]
+pub enum PlainField {
+    Name,
+    Path,
+}
+
+/// Something that can be printed in all of the formats,
+/// one record becomes one row, line or JSON object.
+pub trait Record {
+    /// The fields a template can refer to, in the order
+    /// they are printed in the csv and tsv formats.
+    const FIELDS: &'static [&'static str];
+    /// The titles of the table columns
+    const TITLES: &'static [&'static str];
+
+    /// The titles of the columns when the table has optional ones,
+    /// all of the records that are printed together have the same.
+    fn table_titles(&self) -> Vec<&'static str> {
+        Self::TITLES.to_vec()
+    }
+
+    /// Returns the value of a field as text.
+    fn field(&self, field: &str) -> String;
+    /// Returns the cells of the table row.
+    fn table_row(&self) -> Vec<String>;
+    /// Returns the line printed by the plain format.
+    fn plain(&self) -> String;
+    /// Returns the JSON object, this is a stable schema (documented
+    /// in the README) so fields may be added but never changed.
+    fn to_json(&self) -> Value;
+}
+
+/// A project together with what is needed to print it.
+pub struct ProjectRecord<'a> {
+    pub project: &'a Project,
+    pub workspace: &'a Path,
+    pub plain: PlainField,
+    /// Adds the created, updated and opened dates to the table
+    pub dates: bool,
+}
+
+/// The titles of the optional date columns.
+const DATE_TITLES: &[&str] = &["Created", "Updated", "Opened"];
+
+/// Formats an optional time as a date, unknown times are empty.
+fn optional_date(time: Option<i64>) -> String {
+    time.map(date::format_date).unwrap_or_default()
+}
+
+impl<'a> Record for ProjectRecord<'a> {
+    const FIELDS: &'static [&'static str] = &["name", "path", "tags", "remote", "archived", "created", "updated", "opened"];
+    const TITLES: &'static [&'static str] = &["Project name", "Tags"];
+
+    fn table_titles(&self) -> Vec<&'static str> {
+        let mut titles = Self::TITLES.to_vec();
+        if self.dates {
+            titles.extend(DATE_TITLES);
+        }
+        titles
+    }
+
+    fn field(&self, field: &str) -> String {
+        let project = self.project;
+        match field {
+            "name" => project.name.clone(),
+            "path" => project.get_path(self.workspace).to_string_lossy().to_string(),
+            "tags" => project.tags.join(","),
+            "remote" => project.remote.clone().unwrap_or_default(),
+            "archived" => project.archived.to_string(),
+            "created" => optional_date(project.created_at),
+            "updated" => optional_date(project.updated_at),
+            "opened" => optional_date(project.last_opened_at),
+            _ => String::new(),
+        }
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        let name = if self.project.archived {
+            format!("{} (archived)", self.project.name)
+        } else {
+            self.project.name.clone()
+        };
+        let mut row = vec![name, self.project.tags.join(", ")];
+        if self.dates {
+            row.extend(["created", "updated", "opened"].iter().map(|field| self.field(field)));
+        }
+        row
+    }
+
+    fn plain(&self) -> String {
+        match self.plain {
+            PlainField::Name => self.field("name"),
+            PlainField::Path => self.field("path"),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let project = self.project;
+        json!({
+            "name": project.name,
+            "path": project.get_path(self.workspace).to_string_lossy(),
+            "tags": project.tags,
+            "remote": project.remote,
+            "archived": project.archived,
+            "created_at": project.created_at,
+            "updated_at": project.updated_at,
+            "last_opened_at": project.last_opened_at,
+        })
+    }
+}
+
+/// A project in one of several workspaces, see "pile list --all-workspaces".
+pub struct WorkspaceProjectRecord<'a> {
+    pub workspace_name: &'a str,
+    pub record: ProjectRecord<'a>,
+}
+
+impl<'a> Record for WorkspaceProjectRecord<'a> {
+    const FIELDS: &'static [&'static str] = &["workspace", "name", "path", "tags", "remote", "archived", "created", "updated", "opened"];
+    const TITLES: &'static [&'static str] = &["Workspace", "Project name", "Tags"];
+
+    fn table_titles(&self) -> Vec<&'static str> {
+        let mut titles = vec!["Workspace"];
+        titles.extend(self.record.table_titles());
+        titles
+    }
+
+    fn field(&self, field: &str) -> String {
+        match field {
+            "workspace" => self.workspace_name.to_string(),
+            field => self.record.field(field),
+        }
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        let mut row = vec![self.workspace_name.to_string()];
+        row.extend(self.record.table_row());
+        row
+    }
+
+    fn plain(&self) -> String {
+        self.record.plain()
+    }
+
+    fn to_json(&self) -> Value {
+        let mut value = self.record.to_json();
+        value["workspace"] = json!(self.workspace_name);
+        value
+    }
+}
+
+/// Wraps the projects so that they can be rendered.
+pub fn project_records<'a>(
+    projects: &'a [Project],
+    workspace: &'a Path,
+    plain: PlainField,
+    dates: bool
+    ) -> Vec<ProjectRecord<'a>> {
+    projects.iter()
+        .map(|project| ProjectRecord {
+            project,
+            workspace,
+            plain,
+            dates,
+        })
+        .collect()
+}
+
+/// Creates an empty table with the look shared by all of the pile tables.
+pub fn new_table(titles: &[&str]) -> Table {
+    let mut table = Table::new();
+    table.set_titles(Row::new(titles.iter().map(|title| Cell::new(title)).collect()));
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table
+}
+
+/// Renders the records in the given format.
+pub fn render<R: Record>(records: &[R], format: &Format) -> Result<String, Errors> {
+    let output = match format {
+        Format::Table => {
+            let titles = records.first().map_or_else(|| R::TITLES.to_vec(), Record::table_titles);
+            let mut table = new_table(&titles);
+            for record in records.iter() {
+                table.add_row(Row::new(
+                    record.table_row().iter().map(|cell| Cell::new(cell)).collect()
+                ));
+            }
+            table.to_string()
+        },
+        Format::Json => {
+            let values: Vec<Value> = records.iter().map(Record::to_json).collect();
+            // serializing a Value can not fail
+            serde_json::to_string_pretty(&values).unwrap_or_default() + "\n"
+        },
+        Format::Csv => separated(records, ',', csv_escape),
+        Format::Tsv => separated(records, '\t', tsv_escape),
+        Format::Plain => records.iter()
+            .map(|record| record.plain() + "\n")
+            .collect(),
+        Format::Template(template) => {
+            validate_template(template, R::FIELDS).map_err(Errors::InvalidTemplate)?;
+            records.iter()
+                .map(|record| fill_template(template, record) + "\n")
+                .collect()
+        },
+    };
+    Ok(output)
+}
+
+/// Prints the values separated by `separator`, with a header row.
+fn separated<R: Record>(records: &[R], separator: char, escape: fn(&str) -> String) -> String {
+    let mut output = R::FIELDS.join(&separator.to_string()) + "\n";
+    for record in records.iter() {
+        let values: Vec<String> = R::FIELDS.iter()
+            .map(|field| escape(&record.field(field)))
+            .collect();
+        output.push_str(&values.join(&separator.to_string()));
+        output.push('\n');
+    }
+    output
+}
+
+/// Quotes a csv value if it needs to be quoted.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Tabs and newlines can not be part of a tsv value.
+fn tsv_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+/// Checks that a template only refers to known fields
+/// and that all of the braces are closed.
+fn validate_template(template: &str, fields: &[&str]) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(format!("unclosed \"{{\" in the template \"{}\"", template)),
+        };
+        let field = &rest[start + 1..end];
+        if !fields.contains(&field) {
+            return Err(format!(
+                "unknown field \"{{{}}}\" in the template, expected one of: {}",
+                field,
+                fields.join(", ")
+            ));
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(())
+}
+
+/// Replaces `{field}` with the value of the field and
+/// expands the `\t`, `\n` and `\\` escapes.
+fn fill_template<R: Record>(template: &str, record: &R) -> String {
+    let mut output = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => output.push('\t'),
+                Some('n') => output.push('\n'),
+                Some('\\') => output.push('\\'),
+                Some(other) => {
+                    output.push('\\');
+                    output.push(other);
+                },
+                None => output.push('\\'),
+            },
+            '{' => {
+                let field: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                output.push_str(&record.field(&field));
+            },
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProjectName;
+
+    fn project(name: &str, tags: &[&str], remote: Option<&str>) -> Project {
+        let mut project = Project::new(
+            ProjectName::new(name).unwrap(),
+            tags.iter().map(|tag| tag.to_string()).collect()
+        );
+        project.remote = remote.map(String::from);
+        project
+    }
+
+    fn render_projects(projects: &[Project], format: &str) -> String {
+        let records = project_records(projects, Path::new("/work"), PlainField::Name, false);
+        render(&records, &format.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_is_an_array_of_objects() {
+        let projects = [project("demo", &["rust", "cli"], Some("git@host:a/\"b\".git"))];
+        let value: Value = serde_json::from_str(&render_projects(&projects, "json")).unwrap();
+        assert_eq!(value, json!([{
+            "name": "demo",
+            "path": "/work/demo",
+            "tags": ["rust", "cli"],
+            "remote": "git@host:a/\"b\".git",
+            "archived": false,
+            "created_at": null,
+            "updated_at": null,
+            "last_opened_at": null,
+        }]));
+        assert_eq!(render_projects(&[], "json"), "[]\n");
+    }
+
+    #[test]
+    fn csv_quotes_what_needs_quoting() {
+        let projects = [
+            project("demo", &["rust", "cli"], Some("say \"hi\"")),
+            project("plain", &[], None),
+        ];
+        assert_eq!(
+            render_projects(&projects, "csv"),
+            "name,path,tags,remote,archived,created,updated,opened\n\
+            demo,/work/demo,\"rust,cli\",\"say \"\"hi\"\"\",false,,,\n\
+            plain,/work/plain,,,false,,,\n"
+        );
+        assert_eq!(csv_escape("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_newlines_and_backslashes() {
+        let projects = [project("demo", &["a,b"], Some("x\ty\\z\nw"))];
+        assert_eq!(
+            render_projects(&projects, "tsv"),
+            "name\tpath\ttags\tremote\tarchived\tcreated\tupdated\topened\n\
+            demo\t/work/demo\ta,b\tx\\ty\\\\z\\nw\tfalse\t\t\t\n"
+        );
+    }
+
+    #[test]
+    fn templates_fill_in_fields_and_escapes() {
+        let projects = [project("demo", &["rust"], None), project("other", &[], None)];
+        assert_eq!(render_projects(&projects, "{name}\\t{tags}\\\\"), "demo\trust\\\nother\t\\\n");
+        assert_eq!(render_projects(&projects, "plain"), "demo\nother\n");
+
+        let records = project_records(&projects, Path::new("/work"), PlainField::Name, false);
+        let unknown = render(&records, &Format::Template(String::from("{size}"))).unwrap_err();
+        assert!(matches!(unknown, Errors::InvalidTemplate(_)));
+        assert!("{name".parse::<Format>().is_err());
+        assert!("yaml".parse::<Format>().is_err());
+    }
+}
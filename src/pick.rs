@@ -0,0 +1,190 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crate::{fuzzy, Project};
+
+/// Returns true if the user can be asked questions, the questions
+/// are written to stderr so that stdout can still be piped.
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Asks the user a yes or no question, anything but yes is a no.
+pub fn confirm(question: &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    if io::stderr().flush().is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Asks the user which of the names was meant by `query`.
+/// Returns None if no valid choice was made.
+pub fn choose(query: &str, names: &[String]) -> Option<usize> {
+    eprintln!("\"{}\" matches several projects:", query);
+    for (i, name) in names.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, name);
+    }
+    eprint!("Pick one [1-{}]: ", names.len());
+    io::stderr().flush().ok()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).ok()?;
+    match answer.trim().parse::<usize>() {
+        Ok(choice) if choice >= 1 && choice <= names.len() => Some(choice - 1),
+        _ => None,
+    }
+}
+
+/// Returns the indices of the projects whose name or tags match
+/// the query, best match first. All projects match an empty query.
+/// A match on the name beats an equally good match on one of the tags.
+pub fn filter(query: &str, projects: &[Project]) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..projects.len()).collect();
+    }
+    let mut matches: Vec<((fuzzy::MatchKind, bool, i64), usize)> = projects.iter()
+        .enumerate()
+        .filter_map(|(i, project)| {
+            let name = fuzzy::score(query, &project.name)
+                .map(|(kind, score)| (kind, true, score));
+            let tags = project.tags.iter()
+                .filter_map(|tag| fuzzy::score(query, tag))
+                .map(|(kind, score)| (kind, false, score));
+            Some((name.into_iter().chain(tags).max()?, i))
+        })
+        .collect();
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Restores the terminal when the picker is done, even if it failed.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = RawTerminal;
+        execute!(io::stderr(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Lets the user pick one of the projects, the list is filtered while
+/// typing. The picker is drawn on stderr. Returns None if it was cancelled.
+pub fn pick(projects: &[Project], query: &str) -> io::Result<Option<usize>> {
+    let _terminal = RawTerminal::enter()?;
+    let mut query = query.to_string();
+    let mut selected = 0;
+    let mut offset = 0;
+
+    loop {
+        let matches = filter(&query, projects);
+        selected = selected.min(matches.len().saturating_sub(1));
+
+        // Scroll so that the selected project is visible
+        let (width, height) = terminal::size()?;
+        let rows = (height as usize).saturating_sub(2).max(1);
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + rows {
+            offset = selected + 1 - rows;
+        }
+
+        draw(projects, &matches, &query, selected, offset, rows, width as usize)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Ok(matches.get(selected).copied()),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') | KeyCode::Char('g') if control => return Ok(None),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p') | KeyCode::Char('k') if control => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Char('n') | KeyCode::Char('j') if control => selected += 1,
+            KeyCode::Char('u') if control => query.clear(),
+            KeyCode::Backspace => {
+                query.pop();
+            },
+            KeyCode::Char(c) if !control => {
+                query.push(c);
+                selected = 0;
+                offset = 0;
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Draws the query line, the matching projects and a counter.
+fn draw(
+    projects: &[Project],
+    matches: &[usize],
+    query: &str,
+    selected: usize,
+    offset: usize,
+    rows: usize,
+    width: usize
+    ) -> io::Result<()> {
+
+    let mut stderr = io::stderr();
+    queue!(
+        stderr,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0),
+        Print(truncate(&format!("> {}", query), width))
+    )?;
+
+    for (row, &index) in matches.iter().enumerate().skip(offset).take(rows) {
+        let project = &projects[index];
+        let mut line = format!("  {}", project.name);
+        if !project.tags.is_empty() {
+            line.push_str(&format!("  [{}]", project.tags.join(", ")));
+        }
+        queue!(stderr, cursor::MoveTo(0, (row - offset + 1) as u16))?;
+        if row == selected {
+            line.replace_range(0..1, ">");
+            queue!(
+                stderr,
+                SetAttribute(Attribute::Reverse),
+                Print(truncate(&line, width)),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(stderr, Print(truncate(&line, width)))?;
+        }
+    }
+
+    queue!(
+        stderr,
+        cursor::MoveTo(0, (rows + 1) as u16),
+        SetAttribute(Attribute::Dim),
+        Print(truncate(
+            &format!("{}/{}  enter: select, esc: cancel", matches.len(), projects.len()),
+            width
+        )),
+        SetAttribute(Attribute::Reset)
+    )?;
+    stderr.flush()
+}
+
+/// Cuts a line so that it fits in the terminal.
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
@@ -0,0 +1,523 @@
+//! A small boolean query language used to filter projects,
+//! for instance `pile list --where 'rust and (cli or tui) and not name:old-*'`.
+//!
+//! * A bare word matches a tag, `tag:py*` matches tags with a glob.
+//! * `field:glob` matches a metadata field, see `FIELDS` for the available fields.
+//!   A field name on its own is an error, use `tag:remote` for a tag with that name.
+//! * `date:glob` matches a YYYY-MM-DD date, `date:<2024-01-01` compares it (also with
+//!   `<=`, `>` and `>=`), see `DATE_FIELDS`. A project without the date does not
+//!   match, so `not opened:...` does match it.
+//! * A quoted string is not a glob, `"c++"` and `tag:'c++*'` only match exactly.
+//! * `archived` matches the archived projects, see `FLAGS`.
+//! * Terms are combined with `and`, `or`, `not` and parentheses,
+//!   `not` binds tighter than `and`, which binds tighter than `or`.
+//!
+//! An expression is compiled to a parameterized SQL condition
+//! on the `projects` table.
+use std::fmt;
+
+/// The metadata fields that can be matched with `field:glob`,
+/// and the column each of them corresponds to.
+pub const FIELDS: &[(&str, &str)] = &[
+    ("name", "projects.name"),
+    ("remote", "projects.remote"),
+];
+
+/// The dates that can be matched with `field:glob` or compared with
+/// `field:<YYYY-MM-DD`, and the column (in seconds since 1970) of each.
+pub const DATE_FIELDS: &[(&str, &str)] = &[
+    ("created", "projects.created_at"),
+    ("updated", "projects.updated_at"),
+    ("opened", "projects.last_opened_at"),
+];
+
+/// The comparisons that a date can start with, longest first.
+const DATE_OPERATORS: &[&str] = &["<=", ">=", "<", ">"];
+
+/// The keywords that match projects for which a column is true,
+/// like `not archived`. A tag with such a name needs `tag:archived`.
+pub const FLAGS: &[(&str, &str)] = &[
+    ("archived", "projects.archived"),
+];
+
+/// A parsed query expression
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Tag(String),
+    Field { column: &'static str, pattern: String },
+    /// A date (YYYY-MM-DD, UTC) compared with `operator`, which is GLOB, =, <, <=, > or >=
+    Date { column: &'static str, operator: &'static str, value: String },
+    Flag { column: &'static str },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// An error that occurred while parsing a query,
+/// the position is the character offset in the query.
+#[derive(Debug)]
+pub struct ParseError {
+    pub query: String,
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} (at character {})", self.message, self.position + 1)?;
+        writeln!(f, "  {}", self.query)?;
+        write!(f, "  {}^", " ".repeat(self.position))
+    }
+}
+
+impl Expr {
+    /// Parses a query expression.
+    pub fn parse(query: &str) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            query,
+            tokens: tokenize(query)?,
+            index: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(parser.error_at(token.position, "expected \"and\", \"or\" or the end of the query")),
+        }
+    }
+
+    /// Compiles the expression into a SQL condition, the values
+    /// of the `?` placeholders are pushed to `params` in order.
+    pub fn to_sql(&self, params: &mut Vec<String>) -> String {
+        match self {
+            Expr::Tag(pattern) => {
+                params.push(pattern.clone());
+                String::from(
+                    "projects.id IN (SELECT project_tags.project_id FROM project_tags
+                    JOIN tags ON tags.id = project_tags.tag_id
+                    WHERE tags.name GLOB ?)"
+                )
+            },
+            Expr::Field { column, pattern } => {
+                params.push(pattern.clone());
+                format!("{} GLOB ?", column)
+            },
+            Expr::Date { column, operator, value } => {
+                params.push(value.clone());
+                // NULL would make "not" drop the projects without the date as well
+                format!("COALESCE(date({}, 'unixepoch') {} ?, 0)", column, operator)
+            },
+            Expr::Flag { column } => format!("{} = 1", column),
+            Expr::Not(expr) => format!("NOT ({})", expr.to_sql(params)),
+            Expr::And(left, right) => {
+                let left = left.to_sql(params);
+                format!("({} AND {})", left, right.to_sql(params))
+            },
+            Expr::Or(left, right) => {
+                let left = left.to_sql(params);
+                format!("({} OR {})", left, right.to_sql(params))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    OpenParen,
+    CloseParen,
+    Colon,
+    /// A bare word, keywords are recognized by the parser
+    Word(String),
+    /// A quoted string, never treated as a keyword
+    Quoted(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let position = i;
+        let kind = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            },
+            '(' => { i += 1; TokenKind::OpenParen },
+            ')' => { i += 1; TokenKind::CloseParen },
+            ':' => { i += 1; TokenKind::Colon },
+            quote @ '"' | quote @ '\'' => {
+                i += 1;
+                let start = i;
+                while i < chars.len() && chars[i] != quote {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(ParseError {
+                        query: query.to_string(),
+                        position,
+                        message: String::from("unterminated string"),
+                    });
+                }
+                i += 1;
+                TokenKind::Quoted(chars[start..i - 1].iter().collect())
+            },
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !"():\"'".contains(chars[i]) {
+                    i += 1;
+                }
+                TokenKind::Word(chars[start..i].iter().collect())
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token { kind: TokenKind::Word(word), .. }) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    /// Position just after the last token, used for errors at the end of the query
+    fn end_position(&self) -> usize {
+        self.query.chars().count()
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> ParseError {
+        ParseError {
+            query: self.query.to_string(),
+            position,
+            message: message.to_string(),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_not()?;
+        while self.peek_keyword("and") {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, ParseError> {
+        if self.peek_keyword("not") {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_term()
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, ParseError> {
+        let end = self.end_position();
+        let Token { kind, position } = match self.next() {
+            None => return Err(self.error_at(end, "unexpected end of the query")),
+            Some(token) => token,
+        };
+
+        let word = match kind {
+            TokenKind::OpenParen => {
+                let expr = self.parse_or()?;
+                return match self.next() {
+                    Some(Token { kind: TokenKind::CloseParen, .. }) => Ok(expr),
+                    Some(token) => Err(self.error_at(token.position, "expected \")\"")),
+                    None => Err(self.error_at(end, "expected \")\"")),
+                };
+            },
+            TokenKind::Quoted(tag) => return Ok(Expr::Tag(escape_glob(&tag))),
+            TokenKind::Word(word) => {
+                if ["and", "or", "not"].iter().any(|k| word.eq_ignore_ascii_case(k)) {
+                    return Err(self.error_at(position, &format!("expected a term, found \"{}\"", word)));
+                }
+                word
+            },
+            TokenKind::CloseParen => return Err(self.error_at(position, "unexpected \")\"")),
+            TokenKind::Colon => return Err(self.error_at(position, "expected a field name before \":\"")),
+        };
+
+        // A bare word is a tag, unless it is followed by a colon
+        // or is one of the flags or fields
+        match self.peek() {
+            Some(Token { kind: TokenKind::Colon, .. }) => { self.next(); },
+            _ => {
+                if let Some((_, column)) = FLAGS.iter().find(|(flag, _)| word.eq_ignore_ascii_case(flag)) {
+                    return Ok(Expr::Flag { column });
+                }
+                if FIELDS.iter().chain(DATE_FIELDS).any(|(field, _)| word.eq_ignore_ascii_case(field)) {
+                    return Err(self.error_at(position, &format!(
+                        "\"{}\" is a field, use {}:<glob> (or tag:{} for the tag)",
+                        word,
+                        word,
+                        word
+                    )));
+                }
+                return Ok(Expr::Tag(word));
+            },
+        }
+
+        let (value, quoted, value_position) = match self.next() {
+            Some(Token { kind: TokenKind::Word(value), position }) => (value, false, position),
+            Some(Token { kind: TokenKind::Quoted(value), position }) => (value, true, position),
+            Some(token) => {
+                return Err(self.error_at(token.position, &format!("expected a value for \"{}\"", word)));
+            },
+            None => return Err(self.error_at(end, &format!("expected a value for \"{}\"", word))),
+        };
+
+        if let Some((_, column)) = DATE_FIELDS.iter().find(|(field, _)| word.eq_ignore_ascii_case(field)) {
+            return self.date(column, value, quoted, value_position);
+        }
+        let pattern = if quoted { escape_glob(&value) } else { value };
+
+        if word.eq_ignore_ascii_case("tag") {
+            return Ok(Expr::Tag(pattern));
+        }
+
+        match FIELDS.iter().find(|(field, _)| word.eq_ignore_ascii_case(field)) {
+            Some((_, column)) => Ok(Expr::Field { column, pattern }),
+            None => {
+                let fields: Vec<&str> = FIELDS.iter().chain(DATE_FIELDS).map(|(field, _)| *field).collect();
+                Err(self.error_at(position, &format!(
+                    "unknown field \"{}\", expected one of: tag, {}",
+                    word,
+                    fields.join(", ")
+                )))
+            }
+        }
+    }
+
+    /// Parses the value of a date field: a glob, a quoted date
+    /// or a comparison with a date, like `<2024-01-01`.
+    fn date(&self, column: &'static str, value: String, quoted: bool, position: usize) -> Result<Expr, ParseError> {
+        if quoted {
+            return Ok(Expr::Date { column, operator: "=", value });
+        }
+        let operator = match DATE_OPERATORS.iter().find(|operator| value.starts_with(*operator)) {
+            Some(operator) => operator,
+            None => return Ok(Expr::Date { column, operator: "GLOB", value }),
+        };
+        let date = &value[operator.len()..];
+        if !is_date(date) {
+            return Err(self.error_at(
+                position + operator.len(),
+                &format!("expected a date like 2024-01-31 after \"{}\"", operator)
+            ));
+        }
+        Ok(Expr::Date { column, operator, value: date.to_string() })
+    }
+}
+
+/// Returns true if the text is a date like 2024-01-31.
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2].iter().zip(parts.iter()).all(|(len, part)| {
+            part.len() == *len && part.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Escapes the characters that are special in a GLOB pattern,
+/// so that a quoted string only matches itself.
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                escaped.push('[');
+                escaped.push(c);
+                escaped.push(']');
+            },
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> Box<Expr> {
+        Box::new(Expr::Tag(name.to_string()))
+    }
+
+    fn error_position(query: &str) -> usize {
+        Expr::parse(query).unwrap_err().position
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_which_binds_tighter_than_or() {
+        assert_eq!(
+            Expr::parse("a or not b and c").unwrap(),
+            Expr::Or(tag("a"), Box::new(Expr::And(Box::new(Expr::Not(tag("b"))), tag("c"))))
+        );
+        assert_eq!(
+            Expr::parse("a and b or c").unwrap(),
+            Expr::Or(Box::new(Expr::And(tag("a"), tag("b"))), tag("c"))
+        );
+        assert_eq!(Expr::parse("not not a").unwrap(), Expr::Not(Box::new(Expr::Not(tag("a")))));
+        // left associative
+        assert_eq!(
+            Expr::parse("a or b or c").unwrap(),
+            Expr::Or(Box::new(Expr::Or(tag("a"), tag("b"))), tag("c"))
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            Expr::parse("(a or b) and c").unwrap(),
+            Expr::And(Box::new(Expr::Or(tag("a"), tag("b"))), tag("c"))
+        );
+        assert_eq!(Expr::parse("((a))").unwrap(), Expr::Tag(String::from("a")));
+        assert_eq!(
+            Expr::parse("not(a or b)").unwrap(),
+            Expr::Not(Box::new(Expr::Or(tag("a"), tag("b"))))
+        );
+    }
+
+    #[test]
+    fn quotes_and_keywords() {
+        assert_eq!(Expr::parse("\"and\" AND 'not'").unwrap(), Expr::And(tag("and"), tag("not")));
+        assert_eq!(Expr::parse("'two words'").unwrap(), Expr::Tag(String::from("two words")));
+        assert_eq!(
+            Expr::parse("name:'my (old) project'").unwrap(),
+            Expr::Field { column: "projects.name", pattern: String::from("my (old) project") }
+        );
+        assert_eq!(
+            Expr::parse("NAME:old-* Or Tag:py*").unwrap(),
+            Expr::Or(
+                Box::new(Expr::Field { column: "projects.name", pattern: String::from("old-*") }),
+                tag("py*")
+            )
+        );
+    }
+
+    #[test]
+    fn globs_and_sql() {
+        let mut params = Vec::new();
+        let sql = Expr::parse("rust* and not remote:*github*").unwrap().to_sql(&mut params);
+        assert_eq!(params, vec![String::from("rust*"), String::from("*github*")]);
+        assert!(sql.starts_with("(projects.id IN (SELECT"), "{}", sql);
+        assert!(sql.contains("tags.name GLOB ?"), "{}", sql);
+        assert!(sql.ends_with(" AND NOT (projects.remote GLOB ?))"), "{}", sql);
+
+        let mut params = Vec::new();
+        assert_eq!(Expr::parse("archived or name:a").unwrap().to_sql(&mut params), "(projects.archived = 1 OR projects.name GLOB ?)");
+        assert_eq!(params, vec![String::from("a")]);
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(error_position(""), 0);
+        assert_eq!(error_position("rust and"), 8);
+        assert_eq!(error_position("rust rust"), 5);
+        assert_eq!(error_position("(rust or go"), 11);
+        assert_eq!(error_position("(rust or go x"), 12);
+        assert_eq!(error_position("rust )"), 5);
+        assert_eq!(error_position("and rust"), 0);
+        assert_eq!(error_position("rust and 'unterminated"), 9);
+        assert_eq!(error_position("size:big"), 0);
+        assert_eq!(error_position("name:"), 5);
+        assert_eq!(error_position("name:(x)"), 5);
+        assert_eq!(error_position(":x"), 0);
+        // in characters, not bytes
+        assert_eq!(error_position("ö and"), 5);
+
+        let error = Expr::parse("rust and").unwrap_err();
+        assert_eq!(error.to_string(), "unexpected end of the query (at character 9)\n  rust and\n          ^");
+    }
+
+    #[test]
+    fn archived_is_a_flag() {
+        assert_eq!(
+            Expr::parse("not archived").unwrap(),
+            Expr::Not(Box::new(Expr::Flag { column: "projects.archived" }))
+        );
+        assert_eq!(Expr::parse("tag:archived").unwrap(), Expr::Tag(String::from("archived")));
+        assert_eq!(Expr::parse("'archived'").unwrap(), Expr::Tag(String::from("archived")));
+    }
+
+    #[test]
+    fn bare_field_names_are_errors() {
+        let error = Expr::parse("rust and not remote").unwrap_err();
+        assert_eq!(error.position, 13);
+        assert!(error.message.contains("remote:<glob>"), "{}", error.message);
+    }
+
+    #[test]
+    fn quoted_strings_match_exactly() {
+        assert_eq!(Expr::parse("\"c++*\"").unwrap(), Expr::Tag(String::from("c++[*]")));
+        assert_eq!(Expr::parse("tag:'a?[b]'").unwrap(), Expr::Tag(String::from("a[?][[]b]")));
+        assert_eq!(
+            Expr::parse("name:'old-*' or name:old-*").unwrap(),
+            Expr::Or(
+                Box::new(Expr::Field { column: "projects.name", pattern: String::from("old-[*]") }),
+                Box::new(Expr::Field { column: "projects.name", pattern: String::from("old-*") })
+            )
+        );
+    }
+
+    #[test]
+    fn dates_are_globbed_or_compared() {
+        assert_eq!(
+            Expr::parse("created:2024-*").unwrap(),
+            Expr::Date { column: "projects.created_at", operator: "GLOB", value: String::from("2024-*") }
+        );
+        assert_eq!(
+            Expr::parse("Opened:>=2024-06-01").unwrap(),
+            Expr::Date { column: "projects.last_opened_at", operator: ">=", value: String::from("2024-06-01") }
+        );
+        assert_eq!(
+            Expr::parse("updated:'2024-06-01'").unwrap(),
+            Expr::Date { column: "projects.updated_at", operator: "=", value: String::from("2024-06-01") }
+        );
+
+        let mut params = Vec::new();
+        let sql = Expr::parse("not created:<2024-01-01").unwrap().to_sql(&mut params);
+        assert_eq!(sql, "NOT (COALESCE(date(projects.created_at, 'unixepoch') < ?, 0))");
+        assert_eq!(params, vec![String::from("2024-01-01")]);
+
+        assert_eq!(error_position("created:<2024"), 9);
+        assert_eq!(error_position("rust and opened:>=yesterday"), 18);
+        assert_eq!(error_position("opened"), 0);
+    }
+}
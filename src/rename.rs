@@ -0,0 +1,153 @@
+use std::path::Path;
+use rusqlite::{Connection, params, NO_PARAMS};
+use crate::{complete, config, git, hooks, Context, Errors};
+
+// Renaming a project moves its directory, so everything that refers to the
+// old path is updated too: the remotes of other projects that were cloned
+// from it, the aliases in the pile.toml of the workspace and the commands in
+// hooks.conf. Only the remotes in the database are part of the rename itself,
+// the rest is done afterwards and a failure is only a warning. The user's own
+// config.toml is shared by all workspaces, so a rename never changes it.
+
+/// Replaces `old` with `new` in the remotes of other projects that point to
+/// the old path (or a directory in it). Returns the names of those projects.
+pub fn update_remotes_in_db(conn: &Connection, old: &Path, new: &Path) -> Result<Vec<String>, Errors> {
+    let (old, new) = (old.to_string_lossy(), new.to_string_lossy());
+    let remotes: Vec<(String, String)> = conn
+        .prepare("SELECT name, remote FROM projects WHERE remote IS NOT NULL")
+        .and_then(|mut stmt| {
+            stmt.query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))
+                .and_then(|rows| rows.collect())
+        })
+        .context(|| "could not read the remotes from the database")?;
+
+    let mut updated = Vec::new();
+    for (name, remote) in remotes {
+        if let Some(remote) = replace_path(&remote, &old, &new) {
+            conn.execute("UPDATE projects SET remote = ?1 WHERE name = ?2", params![remote, name])
+                .context(|| format!("could not change the remote of \"{}\" in the database", name))?;
+            updated.push(name);
+        }
+    }
+    Ok(updated)
+}
+
+/// Updates what refers to a project outside of the database after it was
+/// renamed. `remotes` are the projects whose remote was changed in the
+/// database, see `update_remotes_in_db`. Returns a warning for each
+/// thing that could not be updated.
+pub fn update_references(workspace: &Path, old_name: &str, new_name: &str, remotes: &[String]) -> Vec<String> {
+    let old_path = workspace.join(old_name);
+    let new_path = workspace.join(new_name);
+    let (old, new) = (old_path.to_string_lossy(), new_path.to_string_lossy());
+    let mut warnings = Vec::new();
+
+    // worktrees of the repository keep the path of its .git directory
+    let has_worktrees = git::is_repository(&new_path)
+        && git::output(&new_path, &["worktree", "list"]).is_some_and(|list| list.lines().count() > 1);
+    if has_worktrees && git::output(&new_path, &["worktree", "repair"]).is_none() {
+        warnings.push(format!("could not repair the git worktrees of {}", new_path.to_string_lossy()));
+    }
+
+    for name in remotes {
+        let path = workspace.join(name);
+        let url = match git::remote_url(&path) {
+            Some(url) => url,
+            None => continue,
+        };
+        if let Some(url) = replace_path(&url, &old, &new) {
+            if git::output(&path, &["remote", "set-url", "origin", &url]).is_none() {
+                warnings.push(format!("could not change the git remote of \"{}\" to {}", name, url));
+            }
+        }
+    }
+
+    let rewrite = |command: &str| {
+        let replaced = replace_path(command, &old, &new);
+        let command = replaced.as_deref().unwrap_or(command);
+        replace_project_argument(command, old_name, new_name).or(replaced)
+    };
+    if let Err(error) = config::rewrite_aliases(&config::workspace_config_path(workspace), rewrite) {
+        warnings.push(format!("could not update the aliases, {}", error));
+    }
+
+    if let Err(error) = hooks::rewrite_commands(workspace, |command| replace_path(command, &old, &new)) {
+        warnings.push(format!("could not update the hooks, {}", error));
+    }
+    warnings
+}
+
+/// Characters that can be part of a project name.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+/// Replaces the path `old` with `new` where it is not followed by more of
+/// a name, so that renaming /projects/app does not change /projects/app-2.
+/// Returns None if there was nothing to replace.
+pub fn replace_path(text: &str, old: &str, new: &str) -> Option<String> {
+    replace(text, old, new, |_, after| !after.is_some_and(is_name_char))
+}
+
+/// Replaces the project name `old` with `new` in an alias that passes it to
+/// a command that takes a project (see `complete::PROJECT_COMMANDS`), like
+/// "open app". Other words are left alone, like "list" in "list --format
+/// plain" or the tag in "list --where tag:app". Returns None if there was
+/// nothing to replace.
+pub fn replace_project_argument(command: &str, old: &str, new: &str) -> Option<String> {
+    let mut words = command.split_whitespace()
+        .map(|word| (word.as_ptr() as usize - command.as_ptr() as usize, word));
+    let (_, subcommand) = words.next()?;
+    if !complete::PROJECT_COMMANDS.contains(&subcommand) {
+        return None;
+    }
+    // The project is the first argument that is not an option, but
+    // the word after an option might be the value of that option
+    let mut after_option = false;
+    for (start, word) in words {
+        if word.starts_with('-') {
+            after_option = !word.contains('=');
+            continue;
+        }
+        let quotes = ['"', '\''].iter()
+            .any(|&q| word.len() > 1 && word.starts_with(q) && word.ends_with(q));
+        let quote = if quotes { 1 } else { 0 };
+        if &word[quote..word.len() - quote] == old {
+            let (start, end) = (start + quote, start + word.len() - quote);
+            return Some(format!("{}{}{}", &command[..start], new, &command[end..]));
+        }
+        if !after_option {
+            return None;
+        }
+        after_option = false;
+    }
+    None
+}
+
+/// Replaces the occurrences of `old` for which `boundary` returns true,
+/// given the characters before and after it.
+pub(crate) fn replace(text: &str, old: &str, new: &str, boundary: impl Fn(Option<char>, Option<char>) -> bool) -> Option<String> {
+    if old.is_empty() {
+        return None;
+    }
+    let mut result = String::new();
+    let mut rest = 0;
+    let mut changed = false;
+    for (start, _) in text.match_indices(old) {
+        if start < rest {
+            continue;
+        }
+        let end = start + old.len();
+        if boundary(text[..start].chars().next_back(), text[end..].chars().next()) {
+            result.push_str(&text[rest..start]);
+            result.push_str(new);
+            rest = end;
+            changed = true;
+        }
+    }
+    if !changed {
+        return None;
+    }
+    result.push_str(&text[rest..]);
+    Some(result)
+}
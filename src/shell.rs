@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+/// The shells that pile can integrate with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("unsupported shell \"{}\", expected bash, zsh or fish", s)),
+        }
+    }
+}
+
+// The completion scripts wrap the static completions generated from the
+// command-line definition. Project names and tags are asked for with
+// "pile __complete -- <words>", which fails when it has nothing to add.
+
+const BASH_COMPLETIONS: &str = r#"
+_pile_dynamic() {
+    local candidates
+    if candidates="$(command pile __complete -- "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null)"; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "${candidates}" -- "${COMP_WORDS[COMP_CWORD]}"))
+    else
+        _pile "$@"
+    fi
+}
+
+complete -F _pile_dynamic -o bashdefault -o default pile
+"#;
+
+const ZSH_COMPLETIONS: &str = r#"
+_pile_dynamic() {
+    local candidates
+    if candidates="$(command pile __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)"; then
+        compadd -- ${(f)candidates}
+    else
+        _pile "$@"
+    fi
+}
+
+if (( $+functions[compdef] )); then
+    compdef _pile_dynamic pile
+fi
+"#;
+
+const FISH_COMPLETIONS: &str = r#"
+function __pile_dynamic
+    set -l words (commandline -opc)[2..-1] (commandline -ct | string collect --allow-empty)
+    command pile __complete -- $words 2>/dev/null
+end
+
+complete -c pile -f -n '__pile_dynamic >/dev/null' -a '(__pile_dynamic)'
+"#;
+
+const BASH_INIT: &str = r#"# pile shell integration, add this line to ~/.bashrc:
+#     eval "$(pile init bash)"
+
+# Jump into a project, or pick one when no name is given,
+# "-" goes back to the previous project
+__CMD__() {
+    local dir
+    if [ $# -eq 0 ]; then
+        dir="$(command pile pick)" && cd "$dir"
+    elif [ "$1" = "-" ]; then
+        dir="$(command pile -)" && cd "$dir"
+    else
+        dir="$(command pile path -- "$@")" && cd "$dir"
+    fi
+}
+
+# Make "pile cd <project>", "pile jump <partial>" and "pile -" work as well
+pile() {
+    local dir
+    if [ "$1" = "cd" ]; then
+        shift
+        __CMD__ "$@"
+    elif [ "$1" = "jump" ] || [ "$1" = "-" ]; then
+        dir="$(command pile "$@")" && cd "$dir"
+    else
+        command pile "$@"
+    fi
+}
+
+_pile_cd() {
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W "$(command pile __complete -- cd "${COMP_WORDS[COMP_CWORD]}" 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+}
+
+complete -F _pile_cd __CMD__
+"#;
+
+const ZSH_INIT: &str = r#"# pile shell integration, add this line to ~/.zshrc:
+#     eval "$(pile init zsh)"
+
+# Jump into a project, or pick one when no name is given,
+# "-" goes back to the previous project
+__CMD__() {
+    local dir
+    if [ $# -eq 0 ]; then
+        dir="$(command pile pick)" && cd "$dir"
+    elif [ "$1" = "-" ]; then
+        dir="$(command pile -)" && cd "$dir"
+    else
+        dir="$(command pile path -- "$@")" && cd "$dir"
+    fi
+}
+
+# Make "pile cd <project>", "pile jump <partial>" and "pile -" work as well
+pile() {
+    local dir
+    if [[ "$1" == "cd" ]]; then
+        shift
+        __CMD__ "$@"
+    elif [[ "$1" == "jump" || "$1" == "-" ]]; then
+        dir="$(command pile "$@")" && cd "$dir"
+    else
+        command pile "$@"
+    fi
+}
+
+_pile_cd() {
+    compadd -- ${(f)"$(command pile __complete -- cd "${words[CURRENT]}" 2>/dev/null)"}
+}
+
+if (( $+functions[compdef] )); then
+    compdef _pile_cd __CMD__
+fi
+"#;
+
+const FISH_INIT: &str = r#"# pile shell integration, add this line to ~/.config/fish/config.fish:
+#     pile init fish | source
+
+# Jump into a project, or pick one when no name is given,
+# "-" goes back to the previous project
+function __CMD__
+    if test (count $argv) -eq 0
+        set -l dir (command pile pick); and cd $dir
+    else if test "$argv[1]" = -
+        set -l dir (command pile -); and cd $dir
+    else
+        set -l dir (command pile path -- $argv); and cd $dir
+    end
+end
+
+# Make "pile cd <project>", "pile jump <partial>" and "pile -" work as well
+function pile
+    if test "$argv[1]" = cd
+        __CMD__ $argv[2..-1]
+    else if test "$argv[1]" = jump; or test "$argv[1]" = -
+        set -l dir (command pile $argv); and cd $dir
+    else
+        command pile $argv
+    end
+end
+
+complete -c __CMD__ -f -a '(command pile __complete -- cd (commandline -ct | string collect --allow-empty) 2>/dev/null)'
+"#;
+
+/// Returns the completion script for pile, `generated` is the
+/// script with the static completions for all of the subcommands.
+pub fn completion_script(shell: Shell, generated: &str) -> String {
+    match shell {
+        Shell::Bash => format!("{}{}", generated, BASH_COMPLETIONS),
+        // the generated script calls the completion function at the end,
+        // which only works when it is autoloaded from a #compdef file
+        Shell::Zsh => format!("{}{}", generated.trim_end().trim_end_matches("_pile \"$@\""), ZSH_COMPLETIONS),
+        Shell::Fish => format!("{}{}", generated, FISH_COMPLETIONS),
+    }
+}
+
+/// Returns the shell script that defines a function named `cmd` which
+/// jumps into a project, followed by the completions for pile itself.
+pub fn init_script(shell: Shell, cmd: &str, completions: &str) -> String {
+    let script = match shell {
+        Shell::Bash => BASH_INIT,
+        Shell::Zsh => ZSH_INIT,
+        Shell::Fish => FISH_INIT,
+    };
+    format!("{}\n{}", script.replace("__CMD__", cmd), completions)
+}
@@ -0,0 +1,97 @@
+use std::cmp::Ordering;
+use std::path::Path;
+use std::str::FromStr;
+use crate::{archive, workspaces, Project};
+
+/// What "pile list --sort" orders the projects by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Name,
+    Created,
+    Updated,
+    Opened,
+    /// The size of the directory (or compressed archive) on disk
+    Size,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(SortKey::Name),
+            "created" => Ok(SortKey::Created),
+            "updated" => Ok(SortKey::Updated),
+            "opened" => Ok(SortKey::Opened),
+            "size" => Ok(SortKey::Size),
+            _ => Err(format!(
+                "unknown sort key \"{}\", expected name, created, updated, opened or size",
+                s
+            )),
+        }
+    }
+}
+
+/// How a list of projects is ordered and how many are kept.
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub key: SortKey,
+    /// Largest or newest first, or names from z to a
+    pub reverse: bool,
+    pub limit: Option<usize>,
+}
+
+/// The value that a project is sorted by.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Text(String),
+    /// Unknown for projects from before the times were recorded
+    Time(Option<i64>),
+    Size(u64),
+}
+
+impl Order {
+    /// Sorts the items, which each have a project in a workspace. Equal
+    /// items keep their order and unknown times come last, also when the
+    /// order is reversed.
+    pub fn apply<'w, T>(&self, items: &mut Vec<T>, project: impl Fn(&T) -> (&Project, &'w Path)) {
+        let mut keyed: Vec<(Key, T)> = items.drain(..)
+            .map(|item| {
+                let (project, workspace) = project(&item);
+                (self.key_of(project, workspace), item)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| self.compare(a, b));
+        items.extend(keyed.into_iter().map(|(_, item)| item));
+    }
+
+    fn compare(&self, a: &Key, b: &Key) -> Ordering {
+        match (a, b) {
+            (Key::Time(None), Key::Time(None)) => Ordering::Equal,
+            (Key::Time(None), _) => Ordering::Greater,
+            (_, Key::Time(None)) => Ordering::Less,
+            _ if self.reverse => b.cmp(a),
+            _ => a.cmp(b),
+        }
+    }
+
+    /// Cuts a sorted list off at the limit.
+    pub fn truncate<T>(&self, items: &mut Vec<T>) {
+        if let Some(limit) = self.limit {
+            items.truncate(limit);
+        }
+    }
+
+    fn key_of(&self, project: &Project, workspace: &Path) -> Key {
+        match self.key {
+            SortKey::Name => Key::Text(project.name.to_lowercase()),
+            SortKey::Created => Key::Time(project.created_at),
+            SortKey::Updated => Key::Time(project.updated_at),
+            SortKey::Opened => Key::Time(project.last_opened_at),
+            SortKey::Size if archive::is_compressed(workspace, project) => {
+                Key::Size(workspaces::disk_usage(&archive::compressed_path(workspace, &project.name)))
+            },
+            SortKey::Size => Key::Size(workspaces::disk_usage(&project.get_path(workspace))),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde_json::{json, Value};
+use crate::git;
+use crate::output::Record;
+use crate::Project;
+
+/// The git status of a project, printed by `pile status`.
+pub struct ProjectStatus<'a> {
+    pub project: &'a Project,
+    pub status: git::Status,
+}
+
+impl<'a> ProjectStatus<'a> {
+    /// Checks if there is work that only exists in this clone:
+    /// uncommitted changes, unpushed commits or stashes.
+    pub fn is_unsaved(&self) -> bool {
+        let status = &self.status;
+        status.dirty
+            || status.stashes > 0
+            || status.ahead_behind.is_none_or(|(ahead, _)| ahead > 0)
+    }
+
+    fn state(&self) -> &'static str {
+        if self.status.dirty { "dirty" } else { "clean" }
+    }
+
+    /// Formatted as "+ahead -behind", empty if there is no upstream.
+    fn ahead_behind(&self) -> String {
+        match self.status.ahead_behind {
+            Some((ahead, behind)) => format!("+{} -{}", ahead, behind),
+            None => String::new(),
+        }
+    }
+}
+
+impl<'a> Record for ProjectStatus<'a> {
+    const FIELDS: &'static [&'static str] = &[
+        "name", "branch", "state", "ahead", "behind", "last_commit", "stashes"
+    ];
+    const TITLES: &'static [&'static str] = &[
+        "Project name", "Branch", "State", "Upstream", "Last commit", "Stashes"
+    ];
+
+    fn field(&self, field: &str) -> String {
+        let status = &self.status;
+        match field {
+            "name" => self.project.name.clone(),
+            "branch" => status.branch.clone(),
+            "state" => self.state().to_string(),
+            "ahead" => status.ahead_behind.map_or(String::new(), |(ahead, _)| ahead.to_string()),
+            "behind" => status.ahead_behind.map_or(String::new(), |(_, behind)| behind.to_string()),
+            "last_commit" => status.last_commit.clone().unwrap_or_default(),
+            "stashes" => status.stashes.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![
+            self.project.name.clone(),
+            self.status.branch.clone(),
+            self.state().to_string(),
+            self.ahead_behind(),
+            self.field("last_commit"),
+            if self.status.stashes > 0 { self.field("stashes") } else { String::new() },
+        ]
+    }
+
+    fn plain(&self) -> String {
+        self.project.name.clone()
+    }
+
+    fn to_json(&self) -> Value {
+        let status = &self.status;
+        json!({
+            "name": self.project.name,
+            "branch": status.branch,
+            "dirty": status.dirty,
+            "ahead": status.ahead_behind.map(|(ahead, _)| ahead),
+            "behind": status.ahead_behind.map(|(_, behind)| behind),
+            "last_commit": status.last_commit,
+            "stashes": status.stashes,
+        })
+    }
+}
@@ -0,0 +1,282 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use crate::{date, git, rename};
+use crate::query::Expr;
+use crate::{Errors, Project};
+
+/// The directory in the workspace where the project templates are kept,
+/// it is hidden so that it is never mistaken for a project.
+const TEMPLATES_DIR: &str = ".templates";
+
+/// A file in the root of a template that lists files which are only
+/// created when a query matches the new project, one per line:
+///
+/// ```text
+/// # <file or directory>: <query>, like "pile list --where"
+/// src/cli.rs: cli
+/// .github: not tag:private
+/// ```
+const CONDITIONS_FILE: &str = ".conditions";
+
+/// Returns the directory of the templates in a workspace.
+pub fn templates_dir(workspace: &Path) -> PathBuf {
+    workspace.join(TEMPLATES_DIR)
+}
+
+/// Returns the directory of a template, which has to exist.
+pub fn template_dir(workspace: &Path, template: &str) -> Result<PathBuf, Errors> {
+    validate_name(template)?;
+    let path = templates_dir(workspace).join(template);
+    if !path.is_dir() {
+        return Err(Errors::TemplateDoesNotExist);
+    }
+    Ok(path)
+}
+
+/// Template names are used as directory names, so they can not contain
+/// path separators or start with a dot.
+fn validate_name(template: &str) -> Result<(), Errors> {
+    let valid = !template.is_empty()
+        && !template.starts_with('.')
+        && !template.contains(['/', '\\']);
+    if !valid {
+        return Err(Errors::InvalidTemplateName);
+    }
+    Ok(())
+}
+
+/// Returns the names of all the templates, sorted.
+pub fn list(workspace: &Path) -> io::Result<Vec<String>> {
+    let dir = templates_dir(workspace);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Creates a new template with a readme to start from.
+pub fn create(workspace: &Path, template: &str) -> Result<PathBuf, Errors> {
+    validate_name(template)?;
+    let path = templates_dir(workspace).join(template);
+    if path.exists() {
+        return Err(Errors::TemplateAlreadyExists);
+    }
+    fs::create_dir_all(&path)?;
+    fs::write(path.join("README.md"), "# {{name}}\n\nCreated by {{author}} on {{date}}.\n")?;
+    fs::write(
+        path.join(CONDITIONS_FILE),
+        "# Files that are only created for some projects, one per line:\n\
+        # <file or directory>: <query>, like \"pile list --where\"\n\
+        # src/cli.rs: cli\n"
+    )?;
+    Ok(path)
+}
+
+/// Creates a template from the files of an existing project. The name of the
+/// project is replaced with {{name}}, in file names as well as in the files,
+/// where it is not part of a longer identifier (see `replace_name`).
+/// Files that git ignores are left out, and so is the .git directory.
+pub fn create_from_project(workspace: &Path, project: &Project, template: &str) -> Result<PathBuf, Errors> {
+    validate_name(template)?;
+    let path = templates_dir(workspace).join(template);
+    if path.exists() {
+        return Err(Errors::TemplateAlreadyExists);
+    }
+    let source = project.get_path(workspace);
+    if !source.is_dir() {
+        return Err(Errors::DirDoesNotExist(source));
+    }
+
+    let files: Vec<PathBuf> = match git::output(&source, &["ls-files", "--cached", "--others", "--exclude-standard", "-z"]) {
+        Some(files) => files.split('\0')
+            .filter(|file| !file.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        None => walk(&source, Path::new(""))?,
+    };
+
+    fs::create_dir_all(&path)?;
+    let result = files.iter().try_for_each(|file| -> Result<(), Errors> {
+        let from = source.join(file);
+        if !from.is_file() {
+            return Ok(());
+        }
+        let name = replace_name(&file.to_string_lossy(), &project.name);
+        let to = path.join(name);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match fs::read_to_string(&from) {
+            Ok(text) => fs::write(&to, replace_name(&text, &project.name))?,
+            // binary files are copied as they are
+            Err(_) => { fs::copy(&from, &to)?; },
+        }
+        Ok(())
+    });
+    if let Err(error) = result {
+        let _ = fs::remove_dir_all(&path);
+        return Err(error);
+    }
+    Ok(path)
+}
+
+/// Replaces the project name with {{name}} where it is not a part of a
+/// longer identifier, so for a project called "map" the word "mapping"
+/// and "map_size" stay as they are, but "map.rs" and "map-cli" change.
+pub fn replace_name(text: &str, name: &str) -> String {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+    rename::replace(text, name, "{{name}}", |before, after| {
+        !before.is_some_and(is_identifier) && !after.is_some_and(is_identifier)
+    }).unwrap_or_else(|| text.to_string())
+}
+
+/// Returns the relative paths of all files below `dir`, except for .git.
+fn walk(root: &Path, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let relative = dir.join(entry.file_name());
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            files.extend(walk(root, &relative)?);
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(files)
+}
+
+/// The values of the template variables for a new project.
+pub struct Variables {
+    values: Vec<(&'static str, String)>,
+}
+
+impl Variables {
+    /// The author is read from the git configuration,
+    /// or the name of the user if git does not know it.
+    pub fn new(project: &Project, workspace: &Path) -> Self {
+        let author = git::output(workspace, &["config", "user.name"])
+            .filter(|name| !name.is_empty())
+            .or_else(|| env::var("USER").ok())
+            .or_else(|| env::var("USERNAME").ok())
+            .unwrap_or_default();
+        // the variables that can be used in templates, as {{name}}
+        let values = vec![
+            ("name", project.name.clone()),
+            ("tags", project.tags.join(", ")),
+            ("date", date::today()),
+            ("author", author),
+        ];
+        Variables { values }
+    }
+
+    /// Replaces {{variable}} with its value, spaces are allowed inside
+    /// of the braces. Unknown variables are left as they are.
+    pub fn substitute(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = match rest.find("}}") {
+                Some(end) => end,
+                None => break,
+            };
+            let variable = rest[2..end].trim();
+            match self.values.iter().find(|(name, _)| *name == variable) {
+                Some((_, value)) => result.push_str(value),
+                None => result.push_str(&rest[..end + 2]),
+            }
+            rest = &rest[end + 2..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// Reads the conditions of a template, see `CONDITIONS_FILE`.
+pub fn conditions(template_dir: &Path) -> Result<Vec<(PathBuf, Expr)>, Errors> {
+    let text = match fs::read_to_string(template_dir.join(CONDITIONS_FILE)) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (path, query) = line.split_once(':').unwrap_or((line, ""));
+            let expr = Expr::parse(query.trim()).map_err(Errors::InvalidQuery)?;
+            Ok((PathBuf::from(path.trim()), expr))
+        })
+        .collect()
+}
+
+/// Copies the template into the project directory, with the variables
+/// substituted in file names and contents. Files under the paths in
+/// `excluded` are not copied. Existing files are never overwritten.
+/// Nothing is copied if a substituted file name would be outside of the
+/// project directory, see `target_path`.
+pub fn apply(
+    template_dir: &Path,
+    destination: &Path,
+    variables: &Variables,
+    excluded: &[PathBuf]
+    ) -> Result<(), Errors> {
+
+    let mut files = Vec::new();
+    for file in walk(template_dir, Path::new(""))? {
+        if file == Path::new(CONDITIONS_FILE) || excluded.iter().any(|path| file.starts_with(path)) {
+            continue;
+        }
+        let to = destination.join(target_path(&file, variables)?);
+        files.push((template_dir.join(&file), to));
+    }
+
+    for (from, to) in files {
+        if to.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match fs::read_to_string(&from) {
+            Ok(text) => fs::write(&to, variables.substitute(&text))?,
+            Err(_) => { fs::copy(&from, &to)?; },
+        }
+    }
+    Ok(())
+}
+
+/// Substitutes the variables in each part of a relative file name. A value
+/// like "../x" (in a tag, say) could point outside of the project, so every
+/// part has to stay a plain file or directory name.
+fn target_path(file: &Path, variables: &Variables) -> Result<PathBuf, Errors> {
+    let mut path = PathBuf::new();
+    for component in file.components() {
+        let name = variables.substitute(&component.as_os_str().to_string_lossy());
+        let plain = matches!(component, Component::Normal(_))
+            && !name.contains(['/', '\\'])
+            && matches!(Path::new(&name).components().next(), Some(Component::Normal(_)));
+        if !plain {
+            return Err(Errors::InvalidTemplate(format!(
+                "the file {} of the template would be called \"{}\", which is not a file name in the project",
+                file.to_string_lossy(),
+                name
+            )));
+        }
+        path.push(name);
+    }
+    Ok(path)
+}
@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::output::{PlainField, ProjectRecord, Record};
+use crate::{date, Context, Errors, Project};
+
+/// The directory in the workspace where removed projects are moved to.
+const TRASH_DIR: &str = ".trash";
+
+/// A project in the trash. Its directory is `.trash/<id>`, next to
+/// the metadata in `.trash/<id>.json`. The id is the name of the
+/// project, with a number added if that project was trashed before.
+pub struct Entry {
+    pub id: String,
+    pub project: Project,
+    /// When the project was removed, in seconds since 1970-01-01
+    pub removed_at: i64,
+}
+
+impl Entry {
+    pub fn path(&self, workspace: &Path) -> PathBuf {
+        trash_dir(workspace).join(&self.id)
+    }
+
+    /// The number added to the id, 1 if there is none.
+    fn number(&self) -> u32 {
+        self.id.strip_prefix(&self.project.name)
+            .and_then(|rest| rest.strip_prefix('~'))
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(1)
+    }
+
+    fn metadata_path(&self, workspace: &Path) -> PathBuf {
+        trash_dir(workspace).join(format!("{}.json", self.id))
+    }
+}
+
+pub fn trash_dir(workspace: &Path) -> PathBuf {
+    workspace.join(TRASH_DIR)
+}
+
+/// Describes a project that is being removed, it is saved next to the
+/// trashed or archived directory so that the project can be restored.
+/// The fields are the same as in the JSON output of "pile list".
+pub fn metadata(project: &Project, workspace: &Path) -> Value {
+    let record = ProjectRecord { project, workspace, plain: PlainField::Name, dates: false };
+    let mut metadata = record.to_json();
+    let now = date::now();
+    metadata["removed"] = json!(date::format_date(now));
+    metadata["removed_at"] = json!(now);
+    metadata
+}
+
+/// Reads a project from the metadata, see `metadata`.
+pub fn project_from_metadata(metadata: &Value) -> Option<Project> {
+    Some(Project {
+        name: metadata["name"].as_str()?.to_string(),
+        tags: metadata["tags"].as_array()?
+            .iter()
+            .filter_map(|tag| Some(tag.as_str()?.to_string()))
+            .collect(),
+        remote: metadata["remote"].as_str().map(String::from),
+        archived: metadata["archived"].as_bool().unwrap_or(false),
+        created_at: metadata["created_at"].as_i64(),
+        updated_at: metadata["updated_at"].as_i64(),
+        last_opened_at: metadata["last_opened_at"].as_i64(),
+    })
+}
+
+/// Moves the directory of a project to the trash and returns the id of the entry.
+pub fn put(workspace: &Path, project: &Project) -> Result<String, Errors> {
+    let dir = trash_dir(workspace);
+    fs::create_dir_all(&dir)?;
+
+    let mut id = project.name.clone();
+    let mut number = 1;
+    while dir.join(&id).exists() || dir.join(format!("{}.json", id)).exists() {
+        number += 1;
+        id = format!("{}~{}", project.name, number);
+    }
+
+    let metadata = metadata(project, workspace);
+    fs::write(dir.join(format!("{}.json", id)), serde_json::to_string_pretty(&metadata).unwrap_or_default())?;
+    if let Err(error) = fs::rename(project.get_path(workspace), dir.join(&id)) {
+        let _ = fs::remove_file(dir.join(format!("{}.json", id)));
+        return Err(error).context(|| format!(
+            "could not move {} to the trash",
+            project.get_path(workspace).to_string_lossy()
+        ));
+    }
+    Ok(id)
+}
+
+/// Returns all of the projects in the trash, the most recently removed first.
+pub fn entries(workspace: &Path) -> Result<Vec<Entry>, Errors> {
+    let dir = trash_dir(workspace);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let id = match path.file_stem() {
+            Some(id) => id.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let metadata: Value = match serde_json::from_str(&fs::read_to_string(&path)?) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if let Some(project) = project_from_metadata(&metadata) {
+            entries.push(Entry {
+                id,
+                project,
+                removed_at: metadata["removed_at"].as_i64().unwrap_or(0),
+            });
+        }
+    }
+    // projects with the same name that were removed in the same second
+    // are ordered by the number in their ids
+    entries.sort_by_key(|entry| std::cmp::Reverse((entry.removed_at, entry.number())));
+    Ok(entries)
+}
+
+/// Finds an entry by its id, or the most recently removed project with the name.
+pub fn find(workspace: &Path, name: &str) -> Result<Entry, Errors> {
+    let mut entries = entries(workspace)?;
+    let index = entries.iter().position(|entry| entry.id == name)
+        .or_else(|| entries.iter().position(|entry| entry.project.name == name))
+        .ok_or(Errors::NotInTrash)?;
+    Ok(entries.swap_remove(index))
+}
+
+/// Moves the directory of an entry back into the workspace and removes
+/// the metadata. The project has to be added to the database as well.
+pub fn take_out(workspace: &Path, entry: &Entry) -> Result<(), Errors> {
+    let path = entry.project.get_path(workspace);
+    if path.exists() {
+        return Err(Errors::DirAlreadyExists(path));
+    }
+    fs::rename(entry.path(workspace), &path)
+        .context(|| format!("could not move {} back", entry.path(workspace).to_string_lossy()))?;
+    fs::remove_file(entry.metadata_path(workspace))?;
+    Ok(())
+}
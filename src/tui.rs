@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use rusqlite::Connection;
+use crate::output::Record;
+use crate::status::ProjectStatus;
+use crate::{git, hooks, pick, Errors, NameRules, Project};
+
+/// The number of lines of the readme shown in the preview.
+const README_LINES: usize = 200;
+
+const HELP: &str =
+    "/ filter  tab tags  o open  r rename  t tags  y copy path  x run  q quit";
+
+/// The part of the screen that gets the keys.
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Projects,
+    Tags,
+    Filter,
+}
+
+/// What the text typed at the bottom of the screen is for.
+enum Prompt {
+    Rename,
+    Tags,
+    Command,
+}
+
+impl Prompt {
+    fn title(&self) -> &'static str {
+        match self {
+            Prompt::Rename => "New name",
+            Prompt::Tags => "Tags (comma separated)",
+            Prompt::Command => "Command",
+        }
+    }
+}
+
+/// What is shown in the preview pane, it is only read once per project.
+struct Preview {
+    status: Option<git::Status>,
+    readme: Option<String>,
+}
+
+/// What a key press asks the event loop to do.
+enum Action {
+    Nothing,
+    Quit,
+    Run(String),
+}
+
+struct App {
+    workspace: PathBuf,
+    conn: Connection,
+    /// How new names are cleaned up when a project is renamed
+    rules: NameRules,
+    projects: Vec<Project>,
+    tags: Vec<String>,
+    filter: String,
+    /// Indices into `projects` of the ones that match the filter and tag
+    visible: Vec<usize>,
+    list: ListState,
+    /// The selected tag, 0 is "all tags"
+    tag_list: ListState,
+    focus: Focus,
+    prompt: Option<(Prompt, String)>,
+    message: String,
+    previews: HashMap<String, Preview>,
+}
+
+impl App {
+    fn new(workspace: PathBuf, conn: Connection, rules: NameRules) -> Result<Self, Errors> {
+        let mut app = App {
+            workspace,
+            conn,
+            rules,
+            projects: Vec::new(),
+            tags: Vec::new(),
+            filter: String::new(),
+            visible: Vec::new(),
+            list: ListState::default().with_selected(Some(0)),
+            tag_list: ListState::default().with_selected(Some(0)),
+            focus: Focus::Projects,
+            prompt: None,
+            message: String::from(HELP),
+            previews: HashMap::new(),
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    /// Reads the projects and tags from the database again,
+    /// after they have been changed.
+    fn reload(&mut self) -> Result<(), Errors> {
+        let selected_tag = self.selected_tag().cloned();
+        self.projects = Project::fetch_from_db(&self.conn, None, None, None)?
+            .into_iter()
+            .filter(|project| !project.archived)
+            .collect();
+        self.tags = Project::all_tags(&self.conn)?;
+        self.previews.clear();
+
+        let tag_index = selected_tag
+            .and_then(|tag| self.tags.iter().position(|t| *t == tag))
+            .map_or(0, |i| i + 1);
+        self.tag_list.select(Some(tag_index));
+        self.update_visible();
+        Ok(())
+    }
+
+    fn selected_tag(&self) -> Option<&String> {
+        match self.tag_list.selected() {
+            Some(i) if i > 0 => self.tags.get(i - 1),
+            _ => None,
+        }
+    }
+
+    fn selected(&self) -> Option<&Project> {
+        let index = *self.visible.get(self.list.selected()?)?;
+        self.projects.get(index)
+    }
+
+    /// Filters the projects again, keeping the same project selected if it is still visible.
+    fn update_visible(&mut self) {
+        let selected_name = self.selected().map(|project| project.name.clone());
+        let tag = self.selected_tag().cloned();
+        self.visible = pick::filter(&self.filter, &self.projects)
+            .into_iter()
+            .filter(|&i| tag.as_ref().is_none_or(|tag| self.projects[i].tags.contains(tag)))
+            .collect();
+
+        let position = selected_name
+            .and_then(|name| self.visible.iter().position(|&i| self.projects[i].name == name));
+        self.list.select(Some(position.unwrap_or(0)));
+    }
+
+    /// Selects the project with the given name, if it is visible.
+    fn select_name(&mut self, name: &str) {
+        if let Some(position) = self.visible.iter().position(|&i| self.projects[i].name == name) {
+            self.list.select(Some(position));
+        }
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let (state, len) = match self.focus {
+            Focus::Tags => (&mut self.tag_list, self.tags.len() + 1),
+            _ => (&mut self.list, self.visible.len()),
+        };
+        let current = state.selected().unwrap_or(0);
+        let next = if down {
+            (current + 1).min(len.saturating_sub(1))
+        } else {
+            current.saturating_sub(1)
+        };
+        state.select(Some(next));
+        if self.focus == Focus::Tags {
+            self.update_visible();
+        }
+    }
+
+    fn preview(&mut self) -> Option<(&Project, &Preview)> {
+        let project = self.selected()?;
+        let name = project.name.clone();
+        let path = project.get_path(&self.workspace);
+        self.previews.entry(name).or_insert_with(|| Preview {
+            status: git::status(&path),
+            readme: read_readme(&path),
+        });
+        let project = self.selected()?;
+        Some((project, self.previews.get(&project.name)?))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if control && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+        if self.prompt.is_some() {
+            return self.handle_prompt_key(key);
+        }
+
+        match self.focus {
+            Focus::Filter => match key.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Tab => self.focus = Focus::Projects,
+                KeyCode::Up => self.move_selection(false),
+                KeyCode::Down => self.move_selection(true),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.update_visible();
+                },
+                KeyCode::Char('u') if control => {
+                    self.filter.clear();
+                    self.update_visible();
+                },
+                KeyCode::Char(c) if !control => {
+                    self.filter.push(c);
+                    self.update_visible();
+                    self.list.select(Some(0));
+                },
+                _ => (),
+            },
+            Focus::Tags => match key.code {
+                KeyCode::Char('q') => return Action::Quit,
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Tab => self.focus = Focus::Projects,
+                KeyCode::Char('/') => self.focus = Focus::Filter,
+                _ => (),
+            },
+            Focus::Projects => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+                KeyCode::Home | KeyCode::Char('g') => self.list.select(Some(0)),
+                KeyCode::End | KeyCode::Char('G') => {
+                    self.list.select(Some(self.visible.len().saturating_sub(1)))
+                },
+                KeyCode::Char('/') => self.focus = Focus::Filter,
+                KeyCode::Tab => self.focus = Focus::Tags,
+                KeyCode::Char('o') | KeyCode::Enter => self.open(),
+                KeyCode::Char('y') => self.copy_path(),
+                KeyCode::Char('r') => {
+                    if let Some(project) = self.selected() {
+                        self.prompt = Some((Prompt::Rename, project.name.clone()));
+                    }
+                },
+                KeyCode::Char('t') => {
+                    if let Some(project) = self.selected() {
+                        self.prompt = Some((Prompt::Tags, project.tags.join(", ")));
+                    }
+                },
+                KeyCode::Char('x') | KeyCode::Char('!') if self.selected().is_some() => {
+                    self.prompt = Some((Prompt::Command, String::new()));
+                },
+                _ => (),
+            },
+        }
+        Action::Nothing
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) -> Action {
+        let text = match self.prompt.as_mut() {
+            Some((_, text)) => text,
+            None => return Action::Nothing,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.prompt = None;
+                self.message = String::from(HELP);
+            },
+            KeyCode::Backspace => {
+                text.pop();
+            },
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => text.clear(),
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Enter => match self.prompt.take() {
+                Some((Prompt::Rename, text)) => self.rename(&text),
+                Some((Prompt::Tags, text)) => self.edit_tags(&text),
+                Some((Prompt::Command, text)) if !text.trim().is_empty() => return Action::Run(text),
+                _ => (),
+            },
+            _ => (),
+        }
+        Action::Nothing
+    }
+
+    fn open(&mut self) {
+        let name = match self.selected() {
+            Some(project) => project.name.clone(),
+            None => return,
+        };
+        self.message = match crate::open_project(name.clone(), self.workspace.clone(), None) {
+            Ok(()) => format!("Opened {}", name),
+            Err(_) => format!("Could not open {}", name),
+        };
+        self.add_hook_output();
+    }
+
+    fn copy_path(&mut self) {
+        let path = match self.selected() {
+            Some(project) => project.get_path(&self.workspace),
+            None => return,
+        };
+        self.message = match crate::copy_to_clipboard(&path.to_string_lossy()) {
+            Ok(()) => format!("Copied {}", path.to_string_lossy()),
+            Err(_) => String::from("Could not copy the path to the clipboard"),
+        };
+    }
+
+    fn rename(&mut self, new_name: &str) {
+        let mut project = match self.selected() {
+            Some(project) => project.clone(),
+            None => return,
+        };
+        let old_name = project.name.clone();
+        let result = crate::rename_project(&mut project, new_name, &self.rules, &self.conn, &self.workspace);
+        self.message = match result {
+            Ok(name) => format!("Renamed {} to {}", old_name, name),
+            Err(Errors::ProjectNameTaken(_)) => format!("The name {} is already in use", new_name),
+            Err(Errors::InvalidProjectName(_, reason)) => format!("The name {} can not be used, {}", new_name, reason),
+            Err(Errors::HookFailed(hook)) => format!("The {} hook failed, {} was not renamed", hook, old_name),
+            Err(_) => format!("Could not rename {}", old_name),
+        };
+        self.add_hook_output();
+        self.reload_and_select(&project.name);
+    }
+
+    fn edit_tags(&mut self, tags: &str) {
+        let mut project = match self.selected() {
+            Some(project) => project.clone(),
+            None => return,
+        };
+        let tags: Vec<String> = tags.split(',').map(String::from).collect();
+        self.message = match crate::retag_project(&mut project, &tags, &self.conn, &self.workspace) {
+            Ok(()) => format!("The tags of {} are {}", project.name, project.tags.join(", ")),
+            Err(Errors::HookFailed(hook)) => format!("The {} hook failed, the tags were not changed", hook),
+            Err(_) => format!("Could not change the tags of {}", project.name),
+        };
+        self.add_hook_output();
+        self.reload_and_select(&project.name);
+    }
+
+    /// Adds what the hooks printed to the message, on the same line,
+    /// since their output is captured while the TUI is on the screen.
+    fn add_hook_output(&mut self) {
+        let output = hooks::take_output();
+        let lines: Vec<&str> = output.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        if !lines.is_empty() {
+            self.message = format!("{} | {}", self.message, lines.join(" | "));
+        }
+    }
+
+    fn reload_and_select(&mut self, name: &str) {
+        if self.reload().is_err() {
+            self.message = String::from("Could not read the projects from the database");
+        }
+        self.select_name(name);
+    }
+}
+
+/// Reads the beginning of the readme in a project directory, if it has one.
+fn read_readme(path: &Path) -> Option<String> {
+    let entries = fs::read_dir(path).ok()?;
+    let readme = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .find(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+            name == "readme" || name.starts_with("readme.")
+        })?;
+    let file = fs::File::open(readme).ok()?;
+    let lines: Vec<String> = io::BufReader::new(file)
+        .lines()
+        .take(README_LINES)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    Some(lines.join("\n"))
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [filter_area, main_area, status_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [tags_area, projects_area, preview_area] = Layout::horizontal([
+        Constraint::Length(20),
+        Constraint::Percentage(35),
+        Constraint::Min(0),
+    ]).areas(main_area);
+
+    let current = app.focus;
+    let focused = |focus: Focus| if current == focus {
+        Style::new().add_modifier(Modifier::BOLD)
+    } else {
+        Style::new().add_modifier(Modifier::DIM)
+    };
+    let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+    // The filter box
+    let filter = Paragraph::new(app.filter.as_str())
+        .block(Block::bordered().title("Filter").border_style(focused(Focus::Filter)));
+    frame.render_widget(filter, filter_area);
+    if app.focus == Focus::Filter && app.prompt.is_none() {
+        let x = filter_area.x + 1 + app.filter.chars().count() as u16;
+        frame.set_cursor_position((x.min(filter_area.right().saturating_sub(2)), filter_area.y + 1));
+    }
+
+    // The tag sidebar
+    let tags = std::iter::once(String::from("all"))
+        .chain(app.tags.iter().cloned());
+    let tags = List::new(tags)
+        .block(Block::bordered().title("Tags").border_style(focused(Focus::Tags)))
+        .highlight_style(highlight);
+    frame.render_stateful_widget(tags, tags_area, &mut app.tag_list);
+
+    // The projects
+    let all_projects = &app.projects;
+    let projects: Vec<Line> = app.visible.iter()
+        .map(|&i| Line::from(all_projects[i].name.as_str()))
+        .collect();
+    let title = format!("Projects ({}/{})", app.visible.len(), app.projects.len());
+    let projects = List::new(projects)
+        .block(Block::bordered().title(title).border_style(focused(Focus::Projects)))
+        .highlight_style(highlight);
+    frame.render_stateful_widget(projects, projects_area, &mut app.list);
+
+    draw_preview(frame, app, preview_area);
+
+    // The status line, or the prompt
+    match &app.prompt {
+        Some((prompt, text)) => {
+            let label = format!("{}: ", prompt.title());
+            let x = status_area.x + (label.chars().count() + text.chars().count()) as u16;
+            frame.render_widget(Line::from(vec![
+                Span::styled(label, Style::new().add_modifier(Modifier::BOLD)),
+                Span::raw(text.as_str()),
+            ]), status_area);
+            frame.set_cursor_position((x.min(status_area.right().saturating_sub(1)), status_area.y));
+        },
+        None => frame.render_widget(
+            Line::styled(app.message.as_str(), Style::new().add_modifier(Modifier::DIM)),
+            status_area
+        ),
+    }
+}
+
+fn draw_preview(frame: &mut Frame, app: &mut App, area: Rect) {
+    let workspace = app.workspace.clone();
+    let block = Block::bordered().title("Preview").border_style(Style::new().add_modifier(Modifier::DIM));
+    let (project, preview) = match app.preview() {
+        Some(preview) => preview,
+        None => {
+            frame.render_widget(Paragraph::new("No projects where found :(").block(block), area);
+            return;
+        }
+    };
+
+    let bold = Style::new().add_modifier(Modifier::BOLD);
+    let field = |title: &str, value: String| Line::from(vec![
+        Span::styled(format!("{:<12}", title), bold),
+        Span::raw(value),
+    ]);
+    let mut lines = vec![
+        field("Path", project.get_path(&workspace).to_string_lossy().to_string()),
+        field("Tags", project.tags.join(", ")),
+    ];
+    if let Some(remote) = &project.remote {
+        lines.push(field("Remote", remote.clone()));
+    }
+    lines.push(Line::default());
+
+    match &preview.status {
+        Some(status) => {
+            // The same columns as "pile status", except for the name
+            let record = ProjectStatus { project, status: status.clone() };
+            for (title, value) in ProjectStatus::TITLES.iter().zip(record.table_row()).skip(1) {
+                if !value.is_empty() {
+                    lines.push(field(title, value));
+                }
+            }
+        },
+        None => lines.push(Line::styled("Not a git repository", Style::new().add_modifier(Modifier::DIM))),
+    }
+
+    if let Some(readme) = &preview.readme {
+        lines.push(Line::default());
+        lines.extend(readme.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block.title(project.name.as_str()))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+/// Runs a command in the project directory, outside of the TUI,
+/// and waits for the user to press enter before going back.
+fn run_command(terminal: &mut DefaultTerminal, app: &mut App, command: String) -> io::Result<()> {
+    let path = match app.selected() {
+        Some(project) => project.get_path(&app.workspace),
+        None => return Ok(()),
+    };
+
+    ratatui::restore();
+    println!("$ {}", command);
+    app.message = run_in_shell(&command, &path);
+    println!("\n{}, press enter to go back to pile", app.message);
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+
+    *terminal = ratatui::try_init()?;
+    terminal.clear()?;
+    // the command may have changed the git status or the readme
+    app.previews.clear();
+    Ok(())
+}
+
+/// Runs a typed command line with the shell, like the hooks, and
+/// returns the message that says how it went.
+fn run_in_shell(command: &str, path: &Path) -> String {
+    match crate::shell_command(command, path).status() {
+        Ok(status) if status.success() => String::from("The command succeeded"),
+        Ok(status) => match status.code() {
+            Some(code) => format!("The command failed with exit code {}", code),
+            None => String::from("The command was killed"),
+        },
+        Err(_) => String::from("Failed to execute the command"),
+    }
+}
+
+/// Runs the TUI until the user quits.
+pub fn run(workspace: PathBuf, conn: Connection, rules: NameRules) -> Result<(), Errors> {
+    let mut app = App::new(workspace, conn, rules)?;
+    let mut terminal = ratatui::try_init()?;
+    // hooks that write to the terminal would garble the screen
+    hooks::capture_output(true);
+    let result = event_loop(&mut terminal, &mut app);
+    hooks::capture_output(false);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), Errors> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => key,
+            _ => continue,
+        };
+        match app.handle_key(key) {
+            Action::Nothing => (),
+            Action::Quit => return Ok(()),
+            Action::Run(command) => run_command(terminal, app, command)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_commands_are_run_with_their_arguments() {
+        let path = std::env::temp_dir().join(format!("pile-tui-test-{}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+
+        let message = run_in_shell("printf '%s' \"two  words\" > out.txt", &path);
+        let output = fs::read_to_string(path.join("out.txt"));
+        let failed = run_in_shell("exit 3", &path);
+        let _ = fs::remove_dir_all(&path);
+
+        assert_eq!(message, "The command succeeded");
+        assert_eq!(output.unwrap(), "two  words");
+        assert_eq!(failed, "The command failed with exit code 3");
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use crate::{Context, Errors};
+
+/// Returns the number of projects in the database of a workspace,
+/// or None if the workspace has no database (yet). The database is
+/// only read, so that listing the workspaces never changes anything.
+pub fn project_count(workspace: &Path) -> Option<i64> {
+    let conn = Connection::open_with_flags(workspace.join("pile.db"), OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    conn.query_row("SELECT COUNT(*) FROM projects", NO_PARAMS, |row| row.get(0)).ok()
+}
+
+/// Returns true if both paths are the same directory.
+pub fn same_directory(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Returns the size in bytes of a file, or of a directory with everything
+/// in it. Symbolic links are not followed and unreadable files are skipped.
+pub fn disk_usage(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Moves a file or directory. Workspaces can be on different file systems,
+/// where renaming does not work, so then it is copied and removed instead.
+/// If removing fails after everything was copied, `to` is complete but part
+/// of `from` is left; the error of removing it is returned then.
+pub fn move_path(from: &Path, to: &Path) -> Result<Option<io::Error>, Errors> {
+    if to.exists() {
+        return Err(Errors::DirAlreadyExists(to.to_path_buf()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(None);
+    }
+
+    let copied = copy(from, to);
+    if let Err(error) = copied {
+        let _ = remove(to);
+        return Err(error).context(|| format!(
+            "could not copy {} to {}",
+            from.to_string_lossy(),
+            to.to_string_lossy()
+        ));
+    }
+    Ok(remove(from).err())
+}
+
+/// Copies a file or a directory with everything in it, symbolic links are
+/// copied as links (on unix, elsewhere the file they point to is copied).
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        if metadata.file_type().is_symlink() {
+            return std::os::unix::fs::symlink(fs::read_link(from)?, to);
+        }
+    }
+    fs::copy(from, to)?;
+    Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
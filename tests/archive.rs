@@ -0,0 +1,89 @@
+//! Archived projects are written as .tar.zst, like the compressed ones,
+//! and removing a compressed project removes its tarball as well.
+
+mod common;
+
+use std::fs;
+use common::Workspace;
+
+#[test]
+fn remove_archive_writes_a_zstd_tarball() {
+    let workspace = Workspace::with_project("demo");
+    fs::write(workspace.path().join("demo").join("notes.txt"), "hello\n").unwrap();
+    assert!(workspace.pile().args(["remove", "demo", "--archive"]).status().unwrap().success());
+    assert!(!workspace.path().join("demo").exists());
+
+    let archive = workspace.path().join(".archive");
+    let tarball = fs::read_dir(&archive).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(".tar.zst"))
+        .expect("no .tar.zst in .archive");
+    let decoder = zstd::Decoder::new(fs::File::open(tarball).unwrap()).unwrap();
+    let files: Vec<String> = tar::Archive::new(decoder).entries().unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert!(files.iter().any(|file| file == "demo/notes.txt"), "{:?}", files);
+}
+
+/// The tarballs in .archive, by file name.
+fn tarballs(workspace: &Workspace) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(workspace.path().join(".archive")).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".tar.zst"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn a_removed_compressed_project_does_not_block_its_name() {
+    let workspace = Workspace::with_project("demo");
+    let pile = |args: &[&str]| workspace.pile().args(args).output().unwrap();
+
+    fs::write(workspace.path().join("demo").join("notes.txt"), "old\n").unwrap();
+    assert!(pile(&["archive", "demo", "--compress"]).status.success());
+    let output = pile(&["remove", "demo"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("the compressed directory has not been removed"), "{}", stdout);
+    let kept = tarballs(&workspace);
+    assert!(kept.len() == 1 && kept[0].starts_with("demo-"), "{:?}", kept);
+
+    let output = pile(&["add", "demo"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    fs::write(workspace.path().join("demo").join("notes.txt"), "new\n").unwrap();
+    let output = pile(&["archive", "demo", "--compress"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(tarballs(&workspace).contains(&String::from("demo.tar.zst")));
+    assert!(pile(&["unarchive", "demo"]).status.success());
+    assert_eq!(fs::read_to_string(workspace.path().join("demo").join("notes.txt")).unwrap(), "new\n");
+}
+
+#[test]
+fn every_remove_mode_handles_the_tarball() {
+    let workspace = Workspace::with_project("demo");
+    let pile = |args: &[&str]| workspace.pile().args(args).output().unwrap();
+    let compress = || {
+        assert!(pile(&["add", "demo"]).status.success());
+        fs::write(workspace.path().join("demo").join("notes.txt"), "hello\n").unwrap();
+        assert!(pile(&["archive", "demo", "--compress"]).status.success());
+    };
+    let tarball = workspace.path().join(".archive").join("demo.tar.zst");
+    assert!(pile(&["remove", "demo", "--delete", "--yes"]).status.success());
+
+    compress();
+    assert!(pile(&["remove", "demo", "--delete", "--yes"]).status.success());
+    assert!(!tarball.exists());
+
+    compress();
+    assert!(pile(&["remove", "demo", "--trash"]).status.success());
+    assert!(!tarball.exists());
+    assert!(pile(&["restore", "demo"]).status.success());
+    assert!(workspace.path().join("demo").join("notes.txt").is_file());
+    assert!(pile(&["remove", "demo", "--delete", "--yes"]).status.success());
+
+    compress();
+    assert!(pile(&["remove", "demo", "--archive"]).status.success());
+    assert!(!tarball.exists());
+    assert_eq!(tarballs(&workspace).len(), 1);
+}
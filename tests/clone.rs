@@ -0,0 +1,40 @@
+//! pile add --clone records where the project was cloned from,
+//! and leaves nothing behind when the clone fails.
+
+mod common;
+
+use pile::{get_connection, Project};
+use common::{git_repository, Workspace};
+
+#[test]
+fn a_failed_clone_leaves_nothing_behind() {
+    let workspace = Workspace::new();
+    let missing = workspace.path().join("no-such-repository");
+
+    let output = workspace.pile()
+        .args(["add", "demo", "--clone", &missing.to_string_lossy()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("git clone failed"));
+    assert!(!workspace.path().join("demo").exists());
+    let conn = get_connection(workspace.path()).unwrap();
+    assert!(!Project::name_taken("demo", &conn).unwrap());
+}
+
+#[test]
+fn the_origin_of_a_clone_is_recorded() {
+    let workspace = Workspace::new();
+    let origin = workspace.path().join("sources").join("origin");
+    git_repository(&origin);
+    let url = origin.to_string_lossy().to_string();
+
+    let output = workspace.pile().args(["add", "demo", "--clone", &url]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(workspace.path().join("demo").join("main.rs").is_file());
+    let conn = get_connection(workspace.path()).unwrap();
+    assert_eq!(Project::get_from_db_by_name("demo", &conn).unwrap().remote, Some(url));
+
+    let output = workspace.pile().args(["fetch"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
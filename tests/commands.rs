@@ -0,0 +1,81 @@
+//! Commands given on the command line are run with their arguments as they are,
+//! in the project directory, and pile exits with their status.
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+use pile::{get_connection, Project, ProjectName};
+use common::Workspace;
+
+fn pile(workspace: &Workspace, args: &[&str]) -> String {
+    let output = workspace.pile().args(args).output().unwrap();
+    assert!(output.status.success(), "pile {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn foreach_keeps_the_quoting_of_arguments() {
+    let workspace = Workspace::with_project("demo");
+    let output = pile(&workspace, &["foreach", "--", "printf", "%s\\n", "a   b", "c d", "$HOME"]);
+    assert!(output.contains("[demo] a   b\n[demo] c d\n[demo] $HOME\n"), "unexpected output: {}", output);
+}
+
+#[test]
+fn execute_keeps_the_quoting_of_arguments() {
+    let workspace = Workspace::with_project("demo");
+    let output = pile(&workspace, &["path", "demo", "--capture", "--execute", "printf", "%s\\n", "a   b", "'c'"]);
+    assert!(output.ends_with("a   b\n'c'\n"), "unexpected output: {}", output);
+}
+
+#[test]
+fn execute_exits_with_the_status_of_the_command() {
+    let workspace = Workspace::with_project("demo");
+    let output = workspace.pile().args(["path", "demo", "-e", "sh", "-c", "exit 42"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(42));
+    let output = pile(&workspace, &["path", "demo", "--capture", "-e", "pwd"]);
+    assert!(output.ends_with(&format!("{}\n", workspace.path().join("demo").to_string_lossy())), "{}", output);
+}
+
+#[test]
+fn execute_shares_stdin_with_the_command() {
+    let workspace = Workspace::with_project("demo");
+    let mut child = workspace.pile()
+        .args(["path", "demo", "-e", "cat"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"typed by the user\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("typed by the user\n"));
+}
+
+#[test]
+fn foreach_stops_after_a_failure_unless_it_keeps_going() {
+    let workspace = Workspace::with_project("a");
+    let conn = get_connection(workspace.path()).unwrap();
+    for name in ["b", "c"] {
+        let project = Project::new(ProjectName::new(name).unwrap(), Vec::new());
+        project.add_to_db(&conn).unwrap();
+        project.create_directory(workspace.path()).unwrap();
+    }
+    let fails_in_b = ["sh", "-c", "test \"$(basename \"$PWD\")\" != b"];
+    let foreach = |options: &[&str]| {
+        let output = workspace.pile().arg("foreach").args(options).arg("--").args(fails_in_b).output().unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let (code, stdout) = foreach(&[]);
+    assert_eq!(code, Some(7));
+    assert!(stdout.contains("1 passed, 1 failed, 1 skipped"), "{}", stdout);
+
+    let (code, stdout) = foreach(&["--keep-going"]);
+    assert_eq!(code, Some(7));
+    assert!(stdout.contains("2 passed, 1 failed, 0 skipped"), "{}", stdout);
+
+    let (code, stdout) = foreach(&["--keep-going", "--where", "not name:b"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.contains("2 passed, 0 failed, 0 skipped"), "{}", stdout);
+}
@@ -0,0 +1,90 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use pile::{get_connection, Project, ProjectName};
+
+/// An empty workspace in the temporary directory, removed when dropped.
+pub struct Workspace(PathBuf);
+
+impl Workspace {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "pile-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Workspace(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn database(&self) -> PathBuf {
+        self.0.join("pile.db")
+    }
+
+    /// A workspace with a valid database and one project in it.
+    pub fn with_project(name: &str) -> Self {
+        let workspace = Workspace::new();
+        let conn = get_connection(workspace.path()).unwrap();
+        let project = Project::new(ProjectName::new(name).unwrap(), vec![String::from("rust")]);
+        project.add_to_db(&conn).unwrap();
+        project.create_directory(workspace.path()).unwrap();
+        workspace
+    }
+
+    /// A command that runs pile in the workspace. The environment is only
+    /// set for the child process, so tests running in parallel do not see
+    /// each other's config, and never the config of the user.
+    pub fn pile(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_pile"));
+        command
+            .env("PILE_WORKSPACE", &self.0)
+            .env("XDG_CONFIG_HOME", self.0.join("config"))
+            .env("HOME", &self.0)
+            .env_remove("PILE_FORMAT");
+        command
+    }
+
+    /// A workspace where pile.db is not an SQLite database at all.
+    pub fn corrupt() -> Self {
+        let workspace = Workspace::new();
+        fs::write(workspace.database(), "this is not a database, just some text ".repeat(200)).unwrap();
+        workspace
+    }
+}
+
+/// Runs git in a directory, with a name and email for the commits.
+pub fn git(path: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(path)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// Creates a git repository with one commit.
+pub fn git_repository(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    fs::write(path.join("main.rs"), "fn main() {}\n").unwrap();
+    git(path, &["init", "--quiet"]);
+    git(path, &["add", "main.rs"]);
+    git(path, &["commit", "--quiet", "-m", "first"]);
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
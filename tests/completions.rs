@@ -0,0 +1,51 @@
+//! The completions of project names and tags come from the database.
+
+mod common;
+
+use std::fs;
+use pile::{get_connection, Project, ProjectName};
+use common::Workspace;
+
+/// Runs "pile __complete -- <words>", returns None if it has nothing to add.
+fn complete(workspace: &Workspace, words: &[&str]) -> Option<String> {
+    let output = workspace.pile().args(["__complete", "--"]).args(words).output().unwrap();
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[test]
+fn projects_and_tags_are_completed() {
+    let workspace = Workspace::with_project("demo");
+    let conn = get_connection(workspace.path()).unwrap();
+    Project::new(ProjectName::new("other").unwrap(), vec![String::from("web")]).add_to_db(&conn).unwrap();
+
+    assert_eq!(complete(&workspace, &["path", "d"]).as_deref(), Some("demo\nother\n"));
+    assert_eq!(complete(&workspace, &["remove", "--delete", ""]).as_deref(), Some("demo\nother\n"));
+    assert_eq!(complete(&workspace, &["list", "--tag", ""]).as_deref(), Some("rust\nweb\n"));
+    assert_eq!(complete(&workspace, &["add", "new", "r"]).as_deref(), Some("rust\nweb\n"));
+    // the subcommands, flags and other arguments are left to the static completions
+    assert_eq!(complete(&workspace, &["pa"]), None);
+    assert_eq!(complete(&workspace, &["path", "--"]), None);
+    assert_eq!(complete(&workspace, &["path", "demo", ""]), None);
+}
+
+#[test]
+fn a_project_added_later_is_completed_without_new_scripts() {
+    let workspace = Workspace::with_project("demo");
+    let output = workspace.pile().args(["completions", "bash"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("pile __complete --"));
+
+    let other = Workspace::with_project("elsewhere");
+    let config = workspace.path().join("config").join("pile");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(
+        config.join("config.toml"),
+        format!("[workspaces]\nother = {:?}\n", other.path().to_string_lossy())
+    ).unwrap();
+    assert_eq!(complete(&workspace, &["-w", "other", "open", ""]).as_deref(), Some("elsewhere\n"));
+
+    assert!(workspace.pile().args(["add", "later"]).output().unwrap().status.success());
+    assert_eq!(complete(&workspace, &["open", ""]).as_deref(), Some("demo\nlater\n"));
+}
@@ -0,0 +1,85 @@
+//! Flags and environment variables win over pile.toml in the workspace,
+//! which wins over the user's config file, and aliases run other commands.
+
+mod common;
+
+use std::fs;
+use pile::{get_connection, Project, ProjectName};
+use common::Workspace;
+
+fn write_user_config(workspace: &Workspace, text: &str) {
+    let dir = workspace.path().join("config").join("pile");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), text).unwrap();
+}
+
+/// Runs pile and returns its exit code and output.
+fn run(command: &mut std::process::Command) -> (Option<i32>, String, String) {
+    let output = command.output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn the_format_comes_from_the_closest_setting() {
+    let workspace = Workspace::with_project("demo");
+    write_user_config(&workspace, "format = \"{name} from the user config\"\n");
+    assert_eq!(run(workspace.pile().arg("list")).1, "demo from the user config\n");
+
+    fs::write(workspace.path().join("pile.toml"), "format = \"{name} from pile.toml\"\n").unwrap();
+    assert_eq!(run(workspace.pile().arg("list")).1, "demo from pile.toml\n");
+    assert_eq!(run(workspace.pile().arg("list").env("PILE_FORMAT", "{name} from the environment")).1, "demo from the environment\n");
+    assert_eq!(
+        run(workspace.pile().args(["list", "--format", "plain"]).env("PILE_FORMAT", "{name} from the environment")).1,
+        "demo\n"
+    );
+}
+
+#[test]
+fn the_workspace_comes_from_the_flag_the_environment_or_the_config() {
+    let workspace = Workspace::with_project("demo");
+    let other = Workspace::with_project("other");
+    let list = || {
+        let mut command = workspace.pile();
+        command.env_remove("PILE_WORKSPACE").args(["list", "--format", "plain"]);
+        command
+    };
+
+    let (code, _, stderr) = run(&mut list());
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("set the PILE_WORKSPACE environment variable"), "{}", stderr);
+
+    write_user_config(&workspace, &format!("workspace = {:?}\n", workspace.path().to_string_lossy()));
+    assert_eq!(run(&mut list()).1, "demo\n");
+    assert_eq!(run(list().env("PILE_WORKSPACE", other.path())).1, "other\n");
+    assert_eq!(run(list().env("PILE_WORKSPACE", "/no/such/workspace").arg("--workspace").arg(other.path())).1, "other\n");
+}
+
+#[test]
+fn aliases_run_other_commands() {
+    let workspace = Workspace::with_project("demo");
+    let conn = get_connection(workspace.path()).unwrap();
+    let mut old = Project::new(ProjectName::new("old").unwrap(), vec![String::from("rust")]);
+    old.add_to_db(&conn).unwrap();
+    old.set_archived(true, &conn).unwrap();
+    write_user_config(&workspace, "[aliases]\n\
+        ls = \"list --format '{name} from the user config'\"\n\
+        rust = \"list --where 'tag:rust' --format plain\"\n\
+        list = \"status\"\n\
+        again = \"rust\"\n");
+    fs::write(workspace.path().join("pile.toml"), "[aliases]\nls = \"list --format '{name} from pile.toml'\"\n").unwrap();
+
+    assert_eq!(run(workspace.pile().arg("ls")).1, "demo from pile.toml\n");
+    // the arguments after the alias are added to the command
+    assert_eq!(run(workspace.pile().arg("rust")).1, "demo\n");
+    assert_eq!(run(workspace.pile().args(["rust", "--all"])).1, "demo\nold\n");
+    // aliases can not replace commands or refer to other aliases
+    assert_eq!(run(workspace.pile().args(["list", "--format", "plain"])).1, "demo\n");
+    assert_eq!(run(workspace.pile().arg("again")).0, Some(2));
+    let (code, _, stderr) = run(workspace.pile().arg("nope"));
+    assert_eq!(code, Some(2));
+    assert!(stderr.contains("\"nope\" is neither a command nor an alias"), "{}", stderr);
+}
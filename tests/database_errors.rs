@@ -0,0 +1,130 @@
+//! A broken pile.db has to be reported as an error, never as a panic.
+
+mod common;
+
+use std::fs;
+use std::time::Duration;
+use pile::{get_connection, Errors, Project, ProjectName};
+use rusqlite::{Connection, OpenFlags};
+use common::Workspace;
+
+fn is_database_error(error: &Errors) -> bool {
+    match error {
+        Errors::DatabaseError(_) => true,
+        Errors::Context(_, error) => is_database_error(error),
+        _ => false,
+    }
+}
+
+#[test]
+fn corrupt_database_is_an_error() {
+    let workspace = Workspace::corrupt();
+    let error = get_connection(workspace.path()).unwrap_err();
+    assert!(is_database_error(&error), "unexpected error: {:?}", error);
+}
+
+#[test]
+fn project_api_on_corrupt_database_returns_errors() {
+    let workspace = Workspace::corrupt();
+    let conn = Connection::open(workspace.database()).unwrap();
+    let mut project = Project::new(ProjectName::new("demo").unwrap(), vec![String::from("rust")]);
+
+    assert!(Project::name_taken("demo", &conn).is_err());
+    assert!(Project::get_from_db_by_name("demo", &conn).is_err());
+    assert!(Project::find("dem", &conn).is_err());
+    assert!(Project::fetch_from_db(&conn, None, None, None).is_err());
+    assert!(Project::fetch_from_db(&conn, Some(String::from("d")), Some(String::from("rust")), None).is_err());
+    assert!(Project::all_tags(&conn).is_err());
+    assert!(Project::remove_from_db_by_name("demo", &conn).is_err());
+    assert!(project.add_to_db(&conn).is_err());
+    assert!(project.edit_tags(&[String::from("cli")], &conn).is_err());
+    assert!(project.edit_name(&ProjectName::new("other").unwrap(), &conn, workspace.path()).is_err());
+    assert!(project.set_archived(true, &conn).is_err());
+}
+
+#[test]
+fn writing_to_read_only_database_returns_errors() {
+    let workspace = Workspace::with_project("demo");
+    let conn = Connection::open_with_flags(workspace.database(), OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+
+    // reading still works
+    assert!(Project::name_taken("demo", &conn).unwrap());
+    let mut project = Project::get_from_db_by_name("demo", &conn).unwrap();
+    assert_eq!(project.tags, vec![String::from("rust")]);
+    assert_eq!(Project::fetch_from_db(&conn, None, None, None).unwrap().len(), 1);
+
+    let other = Project::new(ProjectName::new("other").unwrap(), Vec::new());
+    assert!(is_database_error(&other.add_to_db(&conn).unwrap_err()));
+    assert!(is_database_error(&Project::remove_from_db_by_name("demo", &conn).unwrap_err()));
+    assert!(is_database_error(&project.edit_tags(&[String::from("cli")], &conn).unwrap_err()));
+    assert!(is_database_error(&project.set_archived(true, &conn).unwrap_err()));
+
+    // nothing was changed
+    assert_eq!(project.tags, vec![String::from("rust")]);
+    assert!(!project.archived);
+    assert!(Project::name_taken("demo", &conn).unwrap());
+}
+
+#[test]
+fn locked_database_returns_errors() {
+    let workspace = Workspace::with_project("demo");
+    let lock = Connection::open(workspace.database()).unwrap();
+    lock.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+    let conn = Connection::open(workspace.database()).unwrap();
+    conn.busy_timeout(Duration::from_millis(0)).unwrap();
+    assert!(Project::name_taken("demo", &conn).is_err());
+    assert!(Project::fetch_from_db(&conn, None, None, None).is_err());
+    assert!(Project::remove_from_db_by_name("demo", &conn).is_err());
+
+    lock.execute_batch("ROLLBACK").unwrap();
+    assert!(Project::name_taken("demo", &conn).unwrap());
+}
+
+/// Runs the pile binary in the workspace, see `Workspace::pile`.
+fn pile(workspace: &Workspace, args: &[&str]) -> std::process::Output {
+    workspace.pile().args(args).output().unwrap()
+}
+
+#[test]
+fn commands_on_corrupt_database_exit_with_database_error() {
+    let workspace = Workspace::corrupt();
+    for args in [
+        &["list"][..],
+        &["path", "demo"],
+        &["add", "demo"],
+        &["remove", "demo"],
+        &["edit", "demo", "--new-name", "other"],
+        &["status"],
+        &["doctor"],
+    ] {
+        let output = pile(&workspace, args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("panicked"), "pile {:?} panicked: {}", args, stderr);
+        assert_eq!(output.status.code(), Some(5), "pile {:?}: {}", args, stderr);
+        assert!(stderr.starts_with("Error: "), "pile {:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn commands_on_read_only_database_do_not_panic() {
+    let workspace = Workspace::with_project("demo");
+    let mut permissions = fs::metadata(workspace.database()).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(workspace.database(), permissions).unwrap();
+
+    for args in [
+        &["add", "other"][..],
+        &["remove", "demo"],
+        &["edit", "demo", "--new-tags", "cli"],
+        &["archive", "demo"],
+    ] {
+        let output = pile(&workspace, args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("panicked"), "pile {:?} panicked: {}", args, stderr);
+        // root can write to read-only files, then the command succeeds
+        if !output.status.success() {
+            assert!(stderr.starts_with("Error: "), "pile {:?}: {}", args, stderr);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! pile doctor --fix says why a fix failed, and fails itself.
+
+mod common;
+
+use std::fs;
+use common::Workspace;
+
+#[test]
+fn failed_fixes_are_explained() {
+    let workspace = Workspace::new();
+    fs::write(workspace.path().join("pile.toml"), "[names]\nlowercase = true\n").unwrap();
+    // "Demo" would be renamed to "demo", which is taken by the other directory
+    fs::create_dir(workspace.path().join("Demo")).unwrap();
+    fs::create_dir(workspace.path().join("demo")).unwrap();
+
+    let output = workspace.pile().args(["doctor", "--fix"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("Fixed: the directory \"demo\" is not in the database"), "{}", stdout);
+    let reason = format!("{} already exists", workspace.path().join("demo").to_string_lossy());
+    assert!(stdout.contains(&format!("Failed to fix: the directory \"Demo\" has an invalid name ({})", reason)), "{}", stdout);
+}
+
+#[test]
+fn orphaned_tarballs_are_reported() {
+    let workspace = Workspace::with_project("demo");
+    let archive = workspace.path().join(".archive");
+    fs::create_dir(&archive).unwrap();
+    fs::write(archive.join("gone.tar.zst"), "").unwrap();
+    // the tarball of a removed project has its metadata next to it
+    fs::write(archive.join("old-2024-01-01.tar.zst"), "").unwrap();
+    fs::write(archive.join("old-2024-01-01.json"), "{}").unwrap();
+
+    let output = workspace.pile().args(["doctor"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\"gone.tar.zst\" in the archive has no archived project"), "{}", stdout);
+    assert!(!stdout.contains("old-2024-01-01"), "{}", stdout);
+
+    assert!(workspace.pile().args(["doctor", "--fix"]).status().unwrap().success());
+    assert!(!archive.join("gone.tar.zst").exists());
+    let output = workspace.pile().args(["doctor"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No problems were found"));
+}
@@ -0,0 +1,75 @@
+//! Projects that are used often and recently come first.
+
+mod common;
+
+use pile::{get_connection, Project, ProjectName};
+use pile::frecency;
+use common::Workspace;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+/// Runs pile in the workspace and returns its output, if it succeeded.
+fn pile(workspace: &Workspace, args: &[&str]) -> Option<String> {
+    let output = workspace.pile().args(args).output().unwrap();
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+#[test]
+fn recent_visits_weigh_more() {
+    let now = 100 * DAY;
+    assert_eq!(frecency::score(3.0, None, now), 0.0);
+    assert_eq!(frecency::score(3.0, Some(now - 10), now), 12.0);
+    assert_eq!(frecency::score(3.0, Some(now - 2 * HOUR), now), 6.0);
+    assert_eq!(frecency::score(3.0, Some(now - 2 * DAY), now), 1.5);
+    assert_eq!(frecency::score(3.0, Some(now - 30 * DAY), now), 0.75);
+}
+
+#[test]
+fn visits_are_aged_and_ordered() {
+    let workspace = Workspace::with_project("first");
+    let conn = get_connection(workspace.path()).unwrap();
+    Project::new(ProjectName::new("second").unwrap(), Vec::new()).add_to_db(&conn).unwrap();
+    assert_eq!(frecency::previous(&conn).unwrap(), None);
+
+    // in the same second, the order of the visits still counts
+    frecency::record_visit("first", DAY, &conn).unwrap();
+    frecency::record_visit("second", DAY, &conn).unwrap();
+    assert_eq!(frecency::previous(&conn).unwrap(), Some(String::from("first")));
+    assert_eq!(frecency::scores(DAY, &conn).unwrap()["second"], 4.0);
+
+    conn.execute("UPDATE projects SET visits = 9999 WHERE name = 'first'", rusqlite::NO_PARAMS).unwrap();
+    frecency::record_visit("first", DAY, &conn).unwrap();
+    let scores = frecency::scores(DAY, &conn).unwrap();
+    let total = (scores["first"] + scores["second"]) / 4.0;
+    assert!((total - 9000.0).abs() < 0.01, "visits were not aged: {}", total);
+    assert!(scores["first"] > scores["second"]);
+}
+
+#[test]
+fn jump_picks_the_most_used_match() {
+    let workspace = Workspace::with_project("rust-tracer");
+    let conn = get_connection(workspace.path()).unwrap();
+    for name in ["rusty", "web-app"] {
+        let project = Project::new(ProjectName::new(name).unwrap(), Vec::new());
+        project.add_to_db(&conn).unwrap();
+        project.create_directory(workspace.path()).unwrap();
+    }
+    let path = |name: &str| workspace.path().join(name).to_string_lossy().to_string();
+
+    assert!(pile(&workspace, &["-"]).is_none());
+    // without visits the closest match wins
+    assert_eq!(pile(&workspace, &["jump", "rust"]), Some(path("rusty")));
+    pile(&workspace, &["path", "rust-tracer"]).unwrap();
+    pile(&workspace, &["path", "rust-tracer"]).unwrap();
+    assert_eq!(pile(&workspace, &["jump", "rust"]), Some(path("rust-tracer")));
+    assert!(pile(&workspace, &["jump", "nothing"]).is_none());
+
+    pile(&workspace, &["path", "web-app"]).unwrap();
+    assert_eq!(pile(&workspace, &["-"]), Some(path("rust-tracer")));
+    assert_eq!(pile(&workspace, &["-"]), Some(path("web-app")));
+    assert_eq!(pile(&workspace, &["recent", "--format", "plain"]), Some(String::from("rust-tracer\nweb-app\nrusty")));
+}
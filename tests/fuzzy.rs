@@ -0,0 +1,34 @@
+//! Partial names find projects, but only for commands that do not change them.
+
+mod common;
+
+use pile::{get_connection, Project};
+use common::Workspace;
+
+#[test]
+fn changing_commands_need_the_exact_name() {
+    let workspace = Workspace::with_project("rust-tracer");
+    let target = Workspace::new();
+    for args in [
+        vec!["archive", "rtr", "--compress"],
+        vec!["unarchive", "rtr"],
+        vec!["edit", "rtr", "--new-name", "other"],
+        vec!["move", "rtr"],
+    ] {
+        let mut command = workspace.pile();
+        command.args(&args);
+        if args[0] == "move" {
+            command.arg("--to").arg(target.path());
+        }
+        let output = command.output().unwrap();
+        assert_eq!(output.status.code(), Some(3), "pile {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+    let conn = get_connection(workspace.path()).unwrap();
+    let project = Project::get_from_db_by_name("rust-tracer", &conn).unwrap();
+    assert!(!project.archived);
+    assert!(workspace.path().join("rust-tracer").is_dir());
+
+    let output = workspace.pile().args(["path", "rtr"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(workspace.pile().args(["archive", "rust-tracer"]).status().unwrap().success());
+}
@@ -0,0 +1,49 @@
+//! A failing pre-hook stops the action, and the output of the hooks
+//! can be captured instead of written to the terminal.
+
+mod common;
+
+use std::fs;
+use pile::hooks::{self, Event, When};
+use pile::{get_connection, Project};
+use common::Workspace;
+
+fn write_hooks(workspace: &Workspace, config: &str) {
+    fs::create_dir(workspace.path().join(".hooks")).unwrap();
+    fs::write(workspace.path().join(".hooks").join("hooks.conf"), config).unwrap();
+}
+
+#[test]
+fn a_failing_pre_hook_stops_the_action() {
+    let workspace = Workspace::with_project("demo");
+    write_hooks(&workspace, "pre-add = exit 1\npre-remove = test \"$PILE_PROJECT_NAME\" != demo\n");
+
+    let output = workspace.pile().args(["add", "other"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the pre-add hook failed"));
+    assert!(!workspace.path().join("other").exists());
+
+    let output = workspace.pile().args(["remove", "demo", "--delete", "--yes"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+    assert!(workspace.path().join("demo").is_dir());
+    let conn = get_connection(workspace.path()).unwrap();
+    assert!(Project::name_taken("demo", &conn).unwrap());
+    assert!(!Project::name_taken("other", &conn).unwrap());
+}
+
+#[test]
+fn the_output_can_be_captured() {
+    let workspace = Workspace::with_project("demo");
+    write_hooks(&workspace, "post-open = echo opened $PILE_PROJECT_NAME; echo oops >&2; exit 1\n");
+    let conn = get_connection(workspace.path()).unwrap();
+    let project = Project::get_from_db_by_name("demo", &conn).unwrap();
+
+    hooks::capture_output(true);
+    let result = hooks::run(workspace.path(), When::Post, Event::Open, &project, &[]);
+    let output = hooks::take_output();
+    hooks::capture_output(false);
+
+    assert!(result.is_ok());
+    assert_eq!(output, "opened demo\noops\nWarning: the post-open hook failed\n");
+    assert_eq!(hooks::take_output(), "");
+}
@@ -0,0 +1,54 @@
+//! Databases from before the migrations are upgraded without losing anything.
+
+mod common;
+
+use rusqlite::{Connection, NO_PARAMS};
+use pile::{get_connection, migrations, Project};
+use common::Workspace;
+
+fn sorted_tags(name: &str, conn: &Connection) -> Vec<String> {
+    let mut tags = Project::get_from_db_by_name(name, conn).unwrap().tags;
+    tags.sort();
+    tags
+}
+
+#[test]
+fn legacy_database_with_comma_separated_tags_is_migrated() {
+    let workspace = Workspace::new();
+    {
+        let legacy = Connection::open(workspace.database()).unwrap();
+        legacy.execute_batch(
+            "CREATE TABLE projects (id integer primary key, name text not null unique, tags text);
+            INSERT INTO projects (name, tags) VALUES ('demo', 'rust, cli');
+            INSERT INTO projects (name, tags) VALUES ('untagged', NULL);
+            INSERT INTO projects (name, tags) VALUES ('messy', ' a,a,, b ,');
+            INSERT INTO projects (name, tags) VALUES ('python', 'py');"
+        ).unwrap();
+    }
+
+    let conn = get_connection(workspace.path()).unwrap();
+    assert_eq!(migrations::current_version(&conn).unwrap(), migrations::latest_version());
+    assert!(migrations::pending(&conn).unwrap().is_empty());
+
+    assert_eq!(sorted_tags("demo", &conn), vec!["cli", "rust"]);
+    assert!(sorted_tags("untagged", &conn).is_empty());
+    assert_eq!(sorted_tags("messy", &conn), vec!["a", "b"]);
+    assert_eq!(sorted_tags("python", &conn), vec!["py"]);
+
+    // the tags are shared and matched exactly
+    let tags: i64 = conn.query_row("SELECT COUNT(*) FROM tags", NO_PARAMS, |row| row.get(0)).unwrap();
+    assert_eq!(tags, 5);
+    let names: Vec<String> = Project::fetch_from_db(&conn, None, Some(String::from("py")), None).unwrap()
+        .into_iter()
+        .map(|project| project.name)
+        .collect();
+    assert_eq!(names, vec!["python"]);
+
+    let project = Project::get_from_db_by_name("demo", &conn).unwrap();
+    assert_eq!((project.remote, project.archived, project.created_at), (None, false, None));
+
+    // opening it again does not migrate anything
+    drop(conn);
+    let mut conn = Connection::open(workspace.database()).unwrap();
+    assert!(migrations::migrate(&mut conn).unwrap().is_empty());
+}
@@ -0,0 +1,62 @@
+//! A project that can not be moved stays where it was.
+
+mod common;
+
+use rusqlite::Connection;
+use pile::{get_connection, Project};
+use common::Workspace;
+
+/// Makes every change of `action` (INSERT or DELETE) to the projects fail.
+fn refuse(workspace: &Workspace, action: &str) {
+    Connection::open(workspace.database()).unwrap().execute_batch(&format!(
+        "CREATE TRIGGER refuse BEFORE {} ON projects BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+        action
+    )).unwrap();
+}
+
+fn is_in(workspace: &Workspace, name: &str) -> bool {
+    Project::name_taken(name, &get_connection(workspace.path()).unwrap()).unwrap()
+}
+
+fn move_demo(workspace: &Workspace, target: &Workspace) -> bool {
+    workspace.pile().args(["move", "demo", "--to"]).arg(target.path()).status().unwrap().success()
+}
+
+#[test]
+fn failed_removal_from_the_source_undoes_the_move() {
+    let workspace = Workspace::with_project("demo");
+    let target = Workspace::new();
+    get_connection(target.path()).unwrap();
+    refuse(&workspace, "DELETE");
+
+    assert!(!move_demo(&workspace, &target));
+    assert!(is_in(&workspace, "demo"));
+    assert!(!is_in(&target, "demo"));
+    assert!(workspace.path().join("demo").is_dir());
+    assert!(!target.path().join("demo").exists());
+}
+
+#[test]
+fn failed_insert_into_the_target_changes_nothing() {
+    let workspace = Workspace::with_project("demo");
+    let target = Workspace::new();
+    get_connection(target.path()).unwrap();
+    refuse(&target, "INSERT");
+
+    assert!(!move_demo(&workspace, &target));
+    assert!(is_in(&workspace, "demo"));
+    assert!(workspace.path().join("demo").is_dir());
+    assert!(!target.path().join("demo").exists());
+}
+
+#[test]
+fn move_changes_both_workspaces() {
+    let workspace = Workspace::with_project("demo");
+    let target = Workspace::new();
+
+    assert!(move_demo(&workspace, &target));
+    assert!(!is_in(&workspace, "demo"));
+    assert!(is_in(&target, "demo"));
+    assert!(!workspace.path().join("demo").exists());
+    assert!(target.path().join("demo").is_dir());
+}
@@ -0,0 +1,98 @@
+//! Project names can never point outside of the workspace.
+
+mod common;
+
+use std::convert::TryFrom;
+use std::fs;
+use pile::{get_connection, Errors, NameRules, Project, ProjectName};
+use pile::names::{CharSet, InvalidName};
+use common::Workspace;
+
+#[test]
+fn unsafe_names_are_rejected() {
+    for (name, reason) in [
+        ("", InvalidName::Empty),
+        (".", InvalidName::Hidden),
+        ("..", InvalidName::Hidden),
+        ("../etc", InvalidName::Hidden),
+        (".trash", InvalidName::Hidden),
+        ("a/b", InvalidName::PathSeparator),
+        ("/etc", InvalidName::PathSeparator),
+        ("a\\b", InvalidName::PathSeparator),
+        ("a\0b", InvalidName::ControlCharacter),
+        ("a\nb", InvalidName::ControlCharacter),
+    ] {
+        assert_eq!(ProjectName::new(name), Err(reason), "{:?}", name);
+    }
+    assert_eq!(ProjectName::new(&"a".repeat(256)), Err(InvalidName::TooLong));
+    assert_eq!(ProjectName::new("rust-tracer.v2").unwrap().as_str(), "rust-tracer.v2");
+}
+
+#[test]
+fn default_rules_only_replace_spaces() {
+    let rules = NameRules::default();
+    assert_eq!(rules.slug("  My Project ").unwrap().as_str(), "My-Project");
+    assert_eq!(rules.slug("Café").unwrap().as_str(), "Café");
+    assert_eq!(rules.slug(" ../etc"), Err(InvalidName::Hidden));
+    assert_eq!(rules.check("My Project"), Err(InvalidName::NotCleanedUp(String::from("My-Project"))));
+}
+
+#[test]
+fn configured_rules_are_applied() {
+    let rules = NameRules {
+        lowercase: true,
+        transliterate: true,
+        allowed: Some(CharSet::try_from(String::from("a-z0-9._-")).unwrap()),
+    };
+    assert_eq!(rules.slug("Café Ünïcode").unwrap().as_str(), "cafe-unicode");
+    assert_eq!(rules.slug("C++ & Rust!").unwrap().as_str(), "c-rust");
+    assert_eq!(rules.slug("a/b").unwrap().as_str(), "a-b");
+    assert_eq!(rules.slug("../etc"), Err(InvalidName::Hidden));
+    assert!(rules.check("cafe").is_ok());
+    assert!(rules.check("Cafe").is_err());
+
+    assert!(CharSet::try_from(String::from("z-a")).is_err());
+}
+
+#[test]
+fn names_that_differ_by_case_conflict() {
+    let workspace = Workspace::with_project("Demo");
+    let conn = get_connection(workspace.path()).unwrap();
+    assert_eq!(Project::conflicting_name("demo", &conn).unwrap(), Some(String::from("Demo")));
+    assert_eq!(Project::conflicting_name("other", &conn).unwrap(), None);
+
+    // but a project can change the case of its own name
+    let mut project = Project::get_from_db_by_name("Demo", &conn).unwrap();
+    project.edit_name(&ProjectName::new("demo").unwrap(), &conn, workspace.path()).unwrap();
+    assert!(Project::name_taken("demo", &conn).unwrap());
+    assert!(workspace.path().join("demo").is_dir());
+}
+
+#[test]
+fn lookups_reject_unsafe_names() {
+    let workspace = Workspace::new();
+    let conn = get_connection(workspace.path()).unwrap();
+    let error = Project::get_from_db_by_name("../etc", &conn).unwrap_err();
+    assert!(matches!(error, Errors::InvalidProjectName(_, InvalidName::Hidden)), "unexpected error: {:?}", error);
+}
+
+#[test]
+fn adding_unsafe_names_fails_without_creating_anything() {
+    let workspace = Workspace::new();
+    let outside = workspace.path().join("outside");
+    let inside = workspace.path().join("inside");
+    fs::create_dir(&inside).unwrap();
+
+    for name in ["../outside", "a/b", ".", "x\u{1}y"] {
+        let output = workspace.pile()
+            .args(["add", name, "--workspace"])
+            .arg(&inside)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "pile add {:?}: {}", name, stderr);
+        assert!(stderr.contains("is not a valid project name"), "pile add {:?}: {}", name, stderr);
+    }
+    assert!(!outside.exists());
+    assert!(!inside.join("a").exists());
+}
@@ -0,0 +1,40 @@
+//! pile list --where filters with a query, and says where a query is wrong.
+
+mod common;
+
+use pile::{get_connection, Project, ProjectName};
+use common::Workspace;
+
+#[test]
+fn where_filters_the_projects() {
+    let workspace = Workspace::with_project("cli");
+    let conn = get_connection(workspace.path()).unwrap();
+    let mut tui = Project::new(ProjectName::new("tui").unwrap(), vec![String::from("rust"), String::from("c++")]);
+    tui.add_to_db(&conn).unwrap();
+    tui.set_archived(true, &conn).unwrap();
+    Project::new(ProjectName::new("web").unwrap(), vec![String::from("c++x")]).add_to_db(&conn).unwrap();
+
+    let list = |query: &str| {
+        let output = workspace.pile().args(["list", "--all", "--format", "plain", "--where", query]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    assert_eq!(list("rust and not archived"), "cli\n");
+    assert_eq!(list("archived or name:w*"), "tui\nweb\n");
+    assert_eq!(list("'c++'"), "tui\n");
+    assert_eq!(list("c++*"), "tui\nweb\n");
+    assert_eq!(list("created:>=2000-01-01 and not opened:>=2000-01-01"), "cli\ntui\nweb\n");
+}
+
+#[test]
+fn errors_show_the_position() {
+    let workspace = Workspace::with_project("demo");
+    let output = workspace.pile().args(["list", "--where", "rust and (cli or"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unexpected end of the query (at character 17)\n  rust and (cli or\n                  ^"),
+        "{}",
+        stderr
+    );
+}
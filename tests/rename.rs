@@ -0,0 +1,130 @@
+//! A rename either happens completely or not at all.
+
+mod common;
+
+use std::fs;
+use pile::{get_connection, Errors, Project, ProjectName};
+use common::Workspace;
+
+fn assert_unchanged(workspace: &Workspace, name: &str) {
+    let conn = get_connection(workspace.path()).unwrap();
+    assert!(Project::name_taken(name, &conn).unwrap());
+    assert!(workspace.path().join(name).is_dir());
+}
+
+#[test]
+fn rename_to_taken_name_changes_nothing() {
+    let workspace = Workspace::with_project("demo");
+    let conn = get_connection(workspace.path()).unwrap();
+    let other = Project::new(ProjectName::new("other").unwrap(), Vec::new());
+    other.add_to_db(&conn).unwrap();
+
+    let mut project = Project::get_from_db_by_name("demo", &conn).unwrap();
+    let error = project.edit_name(&ProjectName::new("other").unwrap(), &conn, workspace.path()).unwrap_err();
+    assert!(matches!(error, Errors::ProjectNameTaken(_)), "unexpected error: {:?}", error);
+    assert_eq!(project.name, "demo");
+    assert_unchanged(&workspace, "demo");
+}
+
+#[test]
+fn rename_onto_existing_directory_changes_nothing() {
+    let workspace = Workspace::with_project("demo");
+    fs::create_dir(workspace.path().join("stray")).unwrap();
+    let conn = get_connection(workspace.path()).unwrap();
+
+    let mut project = Project::get_from_db_by_name("demo", &conn).unwrap();
+    let error = project.edit_name(&ProjectName::new("stray").unwrap(), &conn, workspace.path()).unwrap_err();
+    assert!(matches!(error, Errors::DirAlreadyExists(_)), "unexpected error: {:?}", error);
+    assert!(!Project::name_taken("stray", &conn).unwrap());
+    assert_unchanged(&workspace, "demo");
+}
+
+#[test]
+fn rename_without_directory_changes_nothing() {
+    let workspace = Workspace::with_project("demo");
+    fs::remove_dir(workspace.path().join("demo")).unwrap();
+    let conn = get_connection(workspace.path()).unwrap();
+
+    let mut project = Project::get_from_db_by_name("demo", &conn).unwrap();
+    let error = project.edit_name(&ProjectName::new("renamed").unwrap(), &conn, workspace.path()).unwrap_err();
+    assert!(matches!(error, Errors::DirDoesNotExist(_)), "unexpected error: {:?}", error);
+    assert!(Project::name_taken("demo", &conn).unwrap());
+    assert!(!Project::name_taken("renamed", &conn).unwrap());
+}
+
+#[test]
+fn rename_updates_remotes_hooks_and_aliases() {
+    let workspace = Workspace::with_project("app");
+    let conn = get_connection(workspace.path()).unwrap();
+    let old_path = workspace.path().join("app").to_string_lossy().to_string();
+    let new_path = workspace.path().join("server").to_string_lossy().to_string();
+
+    let mut clone = Project::new(ProjectName::new("clone").unwrap(), Vec::new());
+    clone.remote = Some(old_path.clone());
+    clone.add_to_db(&conn).unwrap();
+    let mut unrelated = Project::new(ProjectName::new("unrelated").unwrap(), Vec::new());
+    unrelated.remote = Some(format!("{}-2", old_path));
+    unrelated.add_to_db(&conn).unwrap();
+
+    fs::create_dir(workspace.path().join(".hooks")).unwrap();
+    fs::write(
+        workspace.path().join(".hooks").join("hooks.conf"),
+        format!("post-open = ls {}/src {}-2\n", old_path, old_path)
+    ).unwrap();
+    fs::write(
+        workspace.path().join("pile.toml"),
+        "[aliases]\n# comment\nserve = \"open app\"\nother = \"open app-2\"\n"
+    ).unwrap();
+
+    let mut project = Project::get_from_db_by_name("app", &conn).unwrap();
+    assert_eq!(project.edit_name(&ProjectName::new("server").unwrap(), &conn, workspace.path()).unwrap(), "server");
+
+    assert!(!workspace.path().join("app").exists());
+    assert!(workspace.path().join("server").is_dir());
+    assert!(Project::name_taken("server", &conn).unwrap());
+    assert!(!Project::name_taken("app", &conn).unwrap());
+    assert_eq!(Project::get_from_db_by_name("clone", &conn).unwrap().remote, Some(new_path.clone()));
+    assert_eq!(Project::get_from_db_by_name("unrelated", &conn).unwrap().remote, Some(format!("{}-2", old_path)));
+    assert_eq!(
+        fs::read_to_string(workspace.path().join(".hooks").join("hooks.conf")).unwrap(),
+        format!("post-open = ls {}/src {}-2\n", new_path, old_path)
+    );
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("pile.toml")).unwrap(),
+        "[aliases]\n# comment\nserve = \"open server\"\nother = \"open app-2\"\n"
+    );
+}
+
+#[test]
+fn rename_leaves_other_uses_of_the_name_alone() {
+    let workspace = Workspace::with_project("list");
+    let conn = get_connection(workspace.path()).unwrap();
+    let aliases = "[aliases]\n\
+        ls = \"list --format plain\"\n\
+        mine = \"list --where 'tag:list and not name:list-*'\"\n\
+        edit-list = \"edit list --tags list\"\n\
+        show = \"path --format plain 'list'\"\n\
+        go = \"cd list-2\"\n";
+    fs::write(workspace.path().join("pile.toml"), aliases).unwrap();
+
+    let mut project = Project::get_from_db_by_name("list", &conn).unwrap();
+    project.edit_name(&ProjectName::new("lst").unwrap(), &conn, workspace.path()).unwrap();
+    assert_eq!(
+        fs::read_to_string(workspace.path().join("pile.toml")).unwrap(),
+        aliases.replace("edit list --tags", "edit lst --tags").replace("'list'", "'lst'")
+    );
+}
+
+#[test]
+fn rename_never_changes_the_user_config() {
+    let workspace = Workspace::with_project("app");
+    let user_config = workspace.path().join("config").join("pile");
+    fs::create_dir_all(&user_config).unwrap();
+    let aliases = "[aliases]\nserve = \"open app\"\n";
+    fs::write(user_config.join("config.toml"), aliases).unwrap();
+
+    let status = workspace.pile().args(["edit", "app", "--new-name", "server"]).status().unwrap();
+    assert!(status.success());
+    assert!(workspace.path().join("server").is_dir());
+    assert_eq!(fs::read_to_string(user_config.join("config.toml")).unwrap(), aliases);
+}
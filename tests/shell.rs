@@ -0,0 +1,47 @@
+//! The functions from pile init change the directory of the shell.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+use common::Workspace;
+
+#[test]
+fn the_bash_function_jumps_into_projects() {
+    let workspace = Workspace::with_project("demo");
+    let binary = Path::new(env!("CARGO_BIN_EXE_pile"));
+    let path = format!("{}:{}", binary.parent().unwrap().to_string_lossy(), std::env::var("PATH").unwrap_or_default());
+    let script = r#"
+        eval "$(pile init bash --cmd goto)" || exit 1
+        goto dem && pwd
+        cd / && pile cd demo && pwd
+        goto nothing-like-it || echo "stayed in $(pwd)"
+    "#;
+
+    let output = Command::new("bash")
+        .args(["--norc", "-c", script])
+        .env("PATH", path)
+        .env("PILE_WORKSPACE", workspace.path())
+        .env("XDG_CONFIG_HOME", workspace.path().join("config"))
+        .env("HOME", workspace.path())
+        .output()
+        .unwrap();
+    let project = workspace.path().join("demo").to_string_lossy().to_string();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n{}\nstayed in {}\n", project, project, project),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn cd_without_the_integration_explains_itself() {
+    let workspace = Workspace::with_project("demo");
+    let output = workspace.pile().args(["cd", "demo"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pile init"));
+
+    let output = workspace.pile().args(["init", "bash", "--cmd", "go to"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
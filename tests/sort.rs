@@ -0,0 +1,32 @@
+//! Reversing the order only reverses the keys.
+
+use std::path::Path;
+use pile::{Project, ProjectName};
+use pile::sort::{Order, SortKey};
+
+fn names(order: &Order) -> Vec<String> {
+    let mut projects: Vec<Project> = [("a", Some(2)), ("b", None), ("c", Some(1)), ("d", Some(2)), ("e", None)]
+        .iter()
+        .map(|&(name, created_at)| {
+            let mut project = Project::new(ProjectName::new(name).unwrap(), Vec::new());
+            project.created_at = created_at;
+            project
+        })
+        .collect();
+    order.apply(&mut projects, |project| (project, Path::new("/work")));
+    order.truncate(&mut projects);
+    projects.into_iter().map(|project| project.name).collect()
+}
+
+#[test]
+fn unknown_dates_are_last_both_ways_and_ties_keep_their_order() {
+    let mut order = Order { key: SortKey::Created, reverse: false, limit: None };
+    assert_eq!(names(&order), vec!["c", "a", "d", "b", "e"]);
+    order.reverse = true;
+    assert_eq!(names(&order), vec!["a", "d", "c", "b", "e"]);
+    order.limit = Some(2);
+    assert_eq!(names(&order), vec!["a", "d"]);
+
+    let order = Order { key: SortKey::Name, reverse: true, limit: None };
+    assert_eq!(names(&order), vec!["e", "d", "c", "b", "a"]);
+}
@@ -0,0 +1,52 @@
+//! pile status shows the git state of the projects that are repositories.
+
+mod common;
+
+use std::fs;
+use serde_json::{json, Value};
+use pile::{get_connection, Project, ProjectName};
+use common::{git, git_repository, Workspace};
+
+#[test]
+fn status_shows_changes_and_unpushed_commits() {
+    let workspace = Workspace::new();
+    let conn = get_connection(workspace.path()).unwrap();
+    for name in ["clean", "dirty", "plain"] {
+        let project = Project::new(ProjectName::new(name).unwrap(), Vec::new());
+        project.add_to_db(&conn).unwrap();
+    }
+    let origin = workspace.path().join("sources").join("origin");
+    git_repository(&origin);
+    git(workspace.path(), &["clone", "--quiet", &origin.to_string_lossy(), "clean"]);
+    git(workspace.path(), &["clone", "--quiet", &origin.to_string_lossy(), "dirty"]);
+    fs::create_dir(workspace.path().join("plain")).unwrap();
+
+    let dirty = workspace.path().join("dirty");
+    fs::write(dirty.join("lib.rs"), "").unwrap();
+    git(&dirty, &["add", "lib.rs"]);
+    git(&dirty, &["commit", "--quiet", "-m", "second"]);
+    fs::write(dirty.join("main.rs"), "fn main() { todo!() }\n").unwrap();
+
+    let output = workspace.pile().args(["status", "--format", "json"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let statuses: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses = statuses.as_array().unwrap();
+    // the directory that is not a repository is left out
+    assert_eq!(statuses.len(), 2, "{:?}", statuses);
+    let branch = statuses[0]["branch"].clone();
+    assert!(branch.is_string());
+    let today = pile::date::today();
+    assert_eq!(statuses[0], json!({
+        "name": "clean", "branch": branch, "dirty": false, "ahead": 0, "behind": 0,
+        "last_commit": today, "stashes": 0,
+    }));
+    assert_eq!(statuses[1], json!({
+        "name": "dirty", "branch": branch, "dirty": true, "ahead": 1, "behind": 0,
+        "last_commit": today, "stashes": 0,
+    }));
+
+    let output = workspace.pile().args(["status", "--unsaved", "--format", "{name} {state}"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("dirty "), "{}", stdout);
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+}
@@ -0,0 +1,50 @@
+//! Templates only ever touch the project name and the project directory.
+
+mod common;
+
+use std::fs;
+use pile::{get_connection, templates, Errors, Project, ProjectName};
+use common::Workspace;
+
+#[test]
+fn from_project_only_replaces_the_whole_name() {
+    let workspace = Workspace::with_project("map");
+    let conn = get_connection(workspace.path()).unwrap();
+    let project = Project::get_from_db_by_name("map", &conn).unwrap();
+    let source = workspace.path().join("map");
+    fs::create_dir(source.join("src")).unwrap();
+    fs::write(source.join("src").join("map.rs"), "use map::Map;\nlet mapping = map_size(\"map-cli\");\n").unwrap();
+    fs::write(source.join("src").join("mapping.rs"), "// remap\n").unwrap();
+
+    let template = templates::create_from_project(workspace.path(), &project, "maps").unwrap();
+    assert_eq!(
+        fs::read_to_string(template.join("src").join("{{name}}.rs")).unwrap(),
+        "use {{name}}::Map;\nlet mapping = map_size(\"{{name}}-cli\");\n"
+    );
+    assert_eq!(fs::read_to_string(template.join("src").join("mapping.rs")).unwrap(), "// remap\n");
+}
+
+#[test]
+fn variables_can_not_point_outside_of_the_project() {
+    let workspace = Workspace::new();
+    let template = templates::create(workspace.path(), "notes").unwrap();
+    fs::create_dir(template.join("{{tags}}")).unwrap();
+    fs::write(template.join("{{tags}}").join("notes.md"), "tagged\n").unwrap();
+    let destination = workspace.path().join("a").join("b").join("demo");
+    fs::create_dir_all(&destination).unwrap();
+
+    for tag in ["../../x", "..", "/tmp/x", "a/b"] {
+        let project = Project::new(ProjectName::new("demo").unwrap(), vec![String::from(tag)]);
+        let variables = templates::Variables::new(&project, workspace.path());
+        let error = templates::apply(&template, &destination, &variables, &[]).unwrap_err();
+        assert!(matches!(error, Errors::InvalidTemplate(_)), "tag {:?}: {:?}", tag, error);
+    }
+    assert!(!workspace.path().join("a").join("x").exists());
+    // nothing is copied when one of the files can not be
+    assert!(!destination.join("README.md").exists());
+
+    let project = Project::new(ProjectName::new("demo").unwrap(), vec![String::from("rust")]);
+    let variables = templates::Variables::new(&project, workspace.path());
+    templates::apply(&template, &destination, &variables, &[]).unwrap();
+    assert_eq!(fs::read_to_string(destination.join("rust").join("notes.md")).unwrap(), "tagged\n");
+}